@@ -0,0 +1,104 @@
+use soroban_sdk::{contracttype, Address, Env};
+
+use crate::{ttl, Error};
+
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Role {
+    Admin,
+    Operator,
+    Pauser,
+    /// Claims directed donations to pay them out to their beneficiaries.
+    Disburser,
+}
+
+#[derive(Clone)]
+#[contracttype]
+pub enum AccessKey {
+    Admin,
+    PendingAdmin,
+    Grant(Role, Address),
+}
+
+pub fn is_initialized(env: &Env) -> bool {
+    env.storage().persistent().has(&AccessKey::Admin)
+}
+
+/// Every admin or role check goes through here, so it also keeps the
+/// instance alive.
+pub fn read_admin(env: &Env) -> Result<Address, Error> {
+    ttl::extend_instance(env);
+    let admin = env
+        .storage()
+        .persistent()
+        .get(&AccessKey::Admin)
+        .ok_or(Error::NotInitialized)?;
+    ttl::extend_persistent(env, &AccessKey::Admin);
+    Ok(admin)
+}
+
+pub fn write_admin(env: &Env, admin: &Address) {
+    env.storage().persistent().set(&AccessKey::Admin, admin);
+    ttl::extend_persistent(env, &AccessKey::Admin);
+}
+
+pub fn read_pending_admin(env: &Env) -> Option<Address> {
+    let pending = env.storage().persistent().get(&AccessKey::PendingAdmin);
+    if pending.is_some() {
+        ttl::extend_persistent(env, &AccessKey::PendingAdmin);
+    }
+    pending
+}
+
+pub fn write_pending_admin(env: &Env, pending: Option<&Address>) {
+    match pending {
+        Some(pending) => {
+            env.storage().persistent().set(&AccessKey::PendingAdmin, pending);
+            ttl::extend_persistent(env, &AccessKey::PendingAdmin);
+        }
+        None => env.storage().persistent().remove(&AccessKey::PendingAdmin),
+    }
+}
+
+/// Bumps the admin entries that exist.
+pub fn extend_ttl(env: &Env) {
+    ttl::extend_persistent_if_present(env, &AccessKey::Admin);
+    ttl::extend_persistent_if_present(env, &AccessKey::PendingAdmin);
+}
+
+/// The admin holds every role; other accounts only hold what was granted to them.
+pub fn has_role(env: &Env, role: Role, account: &Address) -> Result<bool, Error> {
+    if *account == read_admin(env)? {
+        return Ok(true);
+    }
+    let key = AccessKey::Grant(role, account.clone());
+    let granted = env.storage().persistent().get(&key).unwrap_or(false);
+    if granted {
+        ttl::extend_persistent(env, &key);
+    }
+    Ok(granted)
+}
+
+/// The admin role itself can't be granted; it is handed over with `transfer_admin`.
+pub fn set_role(env: &Env, role: Role, account: &Address, granted: bool) -> Result<(), Error> {
+    if role == Role::Admin {
+        return Err(Error::InvalidRole);
+    }
+    let key = AccessKey::Grant(role, account.clone());
+    if granted {
+        env.storage().persistent().set(&key, &true);
+        ttl::extend_persistent(env, &key);
+    } else {
+        env.storage().persistent().remove(&key);
+    }
+    Ok(())
+}
+
+/// Authenticates `caller` and checks that it holds `role`.
+pub fn require_role(env: &Env, caller: &Address, role: Role) -> Result<(), Error> {
+    caller.require_auth();
+    if !has_role(env, role, caller)? {
+        return Err(Error::Unauthorized);
+    }
+    Ok(())
+}
@@ -0,0 +1,56 @@
+use soroban_sdk::contracttype;
+
+use crate::Error;
+
+pub const BPS_DENOMINATOR: u32 = 10_000;
+
+/// How each invested USDC is split across the fund, in basis points.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct Allocation {
+    pub senior_bps: u32,
+    pub subordinated_bps: u32,
+    pub reserve_bps: u32,
+    pub donation_bps: u32,
+}
+
+/// USDC amounts routed to each part of the fund for one investment.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Split {
+    pub senior: i128,
+    pub subordinated: i128,
+    pub reserve: i128,
+    pub donation: i128,
+}
+
+pub fn validate(allocation: &Allocation) -> Result<(), Error> {
+    let total = allocation.senior_bps as u64
+        + allocation.subordinated_bps as u64
+        + allocation.reserve_bps as u64
+        + allocation.donation_bps as u64;
+    if total != BPS_DENOMINATOR as u64 {
+        return Err(Error::InvalidAllocation);
+    }
+    Ok(())
+}
+
+fn portion(amount: i128, bps: u32) -> i128 {
+    amount * bps as i128 / BPS_DENOMINATOR as i128
+}
+
+/// Splits `amount` according to `allocation`.
+///
+/// Every part but senior is rounded down and senior takes whatever is left,
+/// so the parts always add back up to `amount`.
+pub fn split(allocation: &Allocation, amount: i128) -> Split {
+    let subordinated = portion(amount, allocation.subordinated_bps);
+    let reserve = portion(amount, allocation.reserve_bps);
+    let donation = portion(amount, allocation.donation_bps);
+
+    Split {
+        senior: amount - subordinated - reserve - donation,
+        subordinated,
+        reserve,
+        donation,
+    }
+}
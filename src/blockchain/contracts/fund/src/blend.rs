@@ -0,0 +1,140 @@
+use soroban_sdk::{
+    auth::{ContractContext, InvokerContractAuthEntry, SubContractInvocation},
+    contracttype, vec, Address, Env, IntoVal, Map, Symbol, Vec,
+};
+
+/// Fixed-point scale of Blend's b-token rate (12 decimals).
+pub const B_RATE_SCALAR: i128 = 1_000_000_000_000;
+
+/// Request types understood by `Pool::submit`.
+pub const REQUEST_SUPPLY_COLLATERAL: u32 = 2;
+pub const REQUEST_WITHDRAW_COLLATERAL: u32 = 3;
+
+/// One action in a Blend `submit` call.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct Request {
+    pub request_type: u32,
+    pub address: Address,
+    pub amount: i128,
+}
+
+/// A user's Blend positions, in b-tokens/d-tokens keyed by reserve index.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct Positions {
+    pub liabilities: Map<u32, i128>,
+    pub collateral: Map<u32, i128>,
+    pub supply: Map<u32, i128>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct ReserveConfig {
+    pub index: u32,
+    pub decimals: u32,
+    pub c_factor: u32,
+    pub l_factor: u32,
+    pub util: u32,
+    pub max_util: u32,
+    pub r_base: u32,
+    pub r_one: u32,
+    pub r_two: u32,
+    pub r_three: u32,
+    pub reactivity: u32,
+    pub supply_cap: i128,
+    pub enabled: bool,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct ReserveData {
+    pub d_rate: i128,
+    pub b_rate: i128,
+    pub ir_mod: i128,
+    pub b_supply: i128,
+    pub d_supply: i128,
+    pub backstop_credit: i128,
+    pub last_time: u64,
+}
+
+/// A Blend reserve as returned by `Pool::get_reserve`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct Reserve {
+    pub asset: Address,
+    pub config: ReserveConfig,
+    pub data: ReserveData,
+    pub scalar: i128,
+}
+
+/// Supplies `amount` of `token` from the fund to the pool as collateral.
+pub fn supply_collateral(env: &Env, pool: &Address, token: &Address, amount: i128) {
+    let fund = env.current_contract_address();
+
+    // The pool pulls the tokens itself, one call below us.
+    env.authorize_as_current_contract(vec![
+        env,
+        InvokerContractAuthEntry::Contract(SubContractInvocation {
+            context: ContractContext {
+                contract: token.clone(),
+                fn_name: Symbol::new(env, "transfer"),
+                args: vec![
+                    env,
+                    fund.into_val(env),
+                    pool.into_val(env),
+                    amount.into_val(env),
+                ],
+            },
+            sub_invocations: vec![env],
+        }),
+    ]);
+
+    submit(env, pool, REQUEST_SUPPLY_COLLATERAL, token, amount, &fund);
+}
+
+/// Withdraws `amount` of `token` collateral from the pool and sends it to `to`.
+pub fn withdraw_collateral(env: &Env, pool: &Address, token: &Address, amount: i128, to: &Address) {
+    submit(env, pool, REQUEST_WITHDRAW_COLLATERAL, token, amount, to);
+}
+
+/// Underlying value of the fund's `token` collateral at the pool's current b-rate.
+pub fn collateral_value(env: &Env, pool: &Address, token: &Address) -> i128 {
+    let reserve = env.invoke_contract::<Reserve>(
+        pool,
+        &Symbol::new(env, "get_reserve"),
+        vec![env, token.into_val(env)],
+    );
+    let positions = env.invoke_contract::<Positions>(
+        pool,
+        &Symbol::new(env, "get_positions"),
+        vec![env, env.current_contract_address().into_val(env)],
+    );
+
+    let b_tokens = positions.collateral.get(reserve.config.index).unwrap_or(0);
+    b_tokens * reserve.data.b_rate / B_RATE_SCALAR
+}
+
+fn submit(env: &Env, pool: &Address, request_type: u32, token: &Address, amount: i128, to: &Address) {
+    let fund = env.current_contract_address();
+    let requests: Vec<Request> = vec![
+        env,
+        Request {
+            request_type,
+            address: token.clone(),
+            amount,
+        },
+    ];
+
+    env.invoke_contract::<Positions>(
+        pool,
+        &Symbol::new(env, "submit"),
+        vec![
+            env,
+            fund.into_val(env),
+            fund.into_val(env),
+            to.into_val(env),
+            requests.into_val(env),
+        ],
+    );
+}
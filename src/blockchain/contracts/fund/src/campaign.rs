@@ -0,0 +1,173 @@
+//! Matching donation campaigns.
+//!
+//! A sponsor escrows `cap` USDC with the fund to match donations made
+//! between `starts_at` and `ends_at` at `match_bps` of their amount, so
+//! 10 000 matches them 1:1. Each match goes where the donation went, the
+//! general pool or a beneficiary's escrow. Once the campaign has ended,
+//! closing it refunds whatever wasn't matched to the sponsor.
+//!
+//! Every donation walks the open campaigns, so an admin has to approve each
+//! one and only `MAX_OPEN_CAMPAIGNS` can be open at a time.
+
+use soroban_sdk::{contracttype, vec, Address, Env, Vec};
+
+use crate::{allocation::BPS_DENOMINATOR, ttl, Error};
+
+/// How many campaigns can be open, ended or not, until they are closed.
+pub const MAX_OPEN_CAMPAIGNS: u32 = 10;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct Campaign {
+    pub id: u64,
+    pub sponsor: Address,
+    pub match_bps: u32,
+    pub cap: i128,
+    pub starts_at: u64,
+    pub ends_at: u64,
+    pub matched: i128,
+    pub donations: u32,
+    pub closed: bool,
+}
+
+/// How far a campaign has got. It is `active` while it is running and
+/// has match money left.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct CampaignProgress {
+    pub id: u64,
+    pub matched: i128,
+    pub remaining: i128,
+    pub donations: u32,
+    pub active: bool,
+}
+
+/// The id counter and the campaigns not closed yet are instance storage;
+/// each campaign is a persistent entry of its own.
+#[derive(Clone)]
+#[contracttype]
+pub enum CampaignKey {
+    NextId,
+    Open,
+    Campaign(u64),
+}
+
+pub fn read(env: &Env, id: u64) -> Option<Campaign> {
+    let key = CampaignKey::Campaign(id);
+    let campaign = env.storage().persistent().get(&key);
+    if campaign.is_some() {
+        ttl::extend_persistent(env, &key);
+    }
+    campaign
+}
+
+fn write(env: &Env, campaign: &Campaign) {
+    let key = CampaignKey::Campaign(campaign.id);
+    env.storage().persistent().set(&key, campaign);
+    ttl::extend_persistent(env, &key);
+}
+
+/// Ids of the campaigns not closed yet, oldest first.
+pub fn open_ids(env: &Env) -> Vec<u64> {
+    env.storage().instance().get(&CampaignKey::Open).unwrap_or(vec![env])
+}
+
+fn is_running(env: &Env, campaign: &Campaign) -> bool {
+    let now = env.ledger().timestamp();
+    !campaign.closed && campaign.starts_at <= now && now <= campaign.ends_at
+}
+
+pub fn progress(env: &Env, campaign: &Campaign) -> CampaignProgress {
+    let remaining = campaign.cap - campaign.matched;
+    CampaignProgress {
+        id: campaign.id,
+        matched: campaign.matched,
+        remaining,
+        donations: campaign.donations,
+        active: is_running(env, campaign) && remaining > 0,
+    }
+}
+
+/// Opens a campaign for `cap` USDC the caller has taken from `sponsor`.
+/// Fails with `TooManyCampaigns` while `MAX_OPEN_CAMPAIGNS` are open.
+pub fn create(
+    env: &Env,
+    sponsor: &Address,
+    match_bps: u32,
+    cap: i128,
+    starts_at: u64,
+    ends_at: u64,
+) -> Result<Campaign, Error> {
+    if match_bps == 0 || cap <= 0 || starts_at >= ends_at || ends_at <= env.ledger().timestamp() {
+        return Err(Error::InvalidCampaign);
+    }
+    let mut open = open_ids(env);
+    if open.len() >= MAX_OPEN_CAMPAIGNS {
+        return Err(Error::TooManyCampaigns);
+    }
+
+    let id: u64 = env.storage().instance().get(&CampaignKey::NextId).unwrap_or(0);
+    env.storage().instance().set(&CampaignKey::NextId, &(id + 1));
+
+    let campaign = Campaign {
+        id,
+        sponsor: sponsor.clone(),
+        match_bps,
+        cap,
+        starts_at,
+        ends_at,
+        matched: 0,
+        donations: 0,
+        closed: false,
+    };
+    write(env, &campaign);
+    open.push_back(id);
+    env.storage().instance().set(&CampaignKey::Open, &open);
+    Ok(campaign)
+}
+
+/// Matches a donation of `amount_usdc` from every running campaign, oldest
+/// first. Returns each campaign that matched it with the amount matched.
+pub fn match_donation(env: &Env, amount_usdc: i128) -> Vec<(Campaign, i128)> {
+    let mut matches = vec![env];
+    for id in open_ids(env).iter() {
+        let Some(mut campaign) = read(env, id) else {
+            continue;
+        };
+        if !is_running(env, &campaign) {
+            continue;
+        }
+        let matched = (amount_usdc * campaign.match_bps as i128 / BPS_DENOMINATOR as i128)
+            .min(campaign.cap - campaign.matched);
+        if matched <= 0 {
+            continue;
+        }
+        campaign.matched += matched;
+        campaign.donations += 1;
+        write(env, &campaign);
+        matches.push_back((campaign, matched));
+    }
+    matches
+}
+
+/// Closes an ended campaign. Returns it with the amount left to refund.
+pub fn close(env: &Env, id: u64) -> Result<(Campaign, i128), Error> {
+    let mut campaign = read(env, id).ok_or(Error::CampaignNotFound)?;
+    if campaign.closed {
+        return Err(Error::CampaignClosed);
+    }
+    if env.ledger().timestamp() <= campaign.ends_at {
+        return Err(Error::CampaignNotEnded);
+    }
+
+    campaign.closed = true;
+    write(env, &campaign);
+    let mut open = open_ids(env);
+    if let Some(index) = open.first_index_of(id) {
+        open.remove(index);
+    }
+    env.storage().instance().set(&CampaignKey::Open, &open);
+
+    let refund = campaign.cap - campaign.matched;
+    Ok((campaign, refund))
+}
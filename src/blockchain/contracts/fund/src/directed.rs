@@ -0,0 +1,158 @@
+//! Donations directed to a particular beneficiary.
+//!
+//! A directed donation is held in escrow under its beneficiary, a farmer,
+//! a cooperative or a region's account, instead of going to the general
+//! pool in `total_fund`. Each entry keeps its own claim deadline, set by
+//! the claim window in force when it was made. Until then the beneficiary,
+//! or a disburser paying out on its behalf, can claim it; after that it
+//! falls back to the general pool.
+//!
+//! An escrow holds one entry per donor, up to `MAX_ESCROWED_DONORS`: giving
+//! again to the same beneficiary adds to the donor's entry and moves its
+//! deadline out, so the entries can't be multiplied with small donations.
+
+use soroban_sdk::{contracttype, vec, Address, Env, Vec};
+
+use crate::{ttl, Error};
+
+pub const DEFAULT_CLAIM_WINDOW: u64 = 180 * 24 * 60 * 60;
+
+/// Most donors one beneficiary's escrow holds at a time.
+pub const MAX_ESCROWED_DONORS: u32 = 50;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct EscrowedDonation {
+    pub donor: Address,
+    pub amount_usdc: i128,
+    pub claimable_until: u64,
+}
+
+/// The window and the running total are instance storage; each
+/// beneficiary's escrow is a persistent entry of its own.
+#[derive(Clone)]
+#[contracttype]
+pub enum DirectedKey {
+    ClaimWindow,
+    TotalEscrowed,
+    Escrow(Address),
+}
+
+pub fn claim_window(env: &Env) -> u64 {
+    env.storage()
+        .instance()
+        .get(&DirectedKey::ClaimWindow)
+        .unwrap_or(DEFAULT_CLAIM_WINDOW)
+}
+
+pub fn set_claim_window(env: &Env, seconds: u64) {
+    env.storage().instance().set(&DirectedKey::ClaimWindow, &seconds);
+}
+
+/// USDC held for beneficiaries, which belongs to neither the investors
+/// nor the general pool.
+pub fn total_escrowed(env: &Env) -> i128 {
+    env.storage().instance().get(&DirectedKey::TotalEscrowed).unwrap_or(0)
+}
+
+fn add_total_escrowed(env: &Env, amount: i128) {
+    env.storage()
+        .instance()
+        .set(&DirectedKey::TotalEscrowed, &(total_escrowed(env) + amount));
+}
+
+/// Donations held for `beneficiary`, in the order their donors first gave.
+pub fn read_escrow(env: &Env, beneficiary: &Address) -> Vec<EscrowedDonation> {
+    let key = DirectedKey::Escrow(beneficiary.clone());
+    match env.storage().persistent().get(&key) {
+        Some(escrow) => {
+            ttl::extend_persistent(env, &key);
+            escrow
+        }
+        None => vec![env],
+    }
+}
+
+fn write_escrow(env: &Env, beneficiary: &Address, escrow: &Vec<EscrowedDonation>) {
+    let key = DirectedKey::Escrow(beneficiary.clone());
+    if escrow.is_empty() {
+        env.storage().persistent().remove(&key);
+    } else {
+        env.storage().persistent().set(&key, escrow);
+        ttl::extend_persistent(env, &key);
+    }
+}
+
+/// Holds `amount_usdc` from `donor` for `beneficiary`, in the donor's
+/// entry if it hasn't expired yet. Fails with `EscrowFull` when a new entry
+/// is needed and `MAX_ESCROWED_DONORS` are already held.
+pub fn deposit(
+    env: &Env,
+    donor: &Address,
+    beneficiary: &Address,
+    amount_usdc: i128,
+) -> Result<EscrowedDonation, Error> {
+    let now = env.ledger().timestamp();
+    let claimable_until = now + claim_window(env);
+    let mut escrow = read_escrow(env, beneficiary);
+    let entry = escrow
+        .iter()
+        .position(|donation| donation.donor == *donor && donation.claimable_until >= now);
+
+    let donation = match entry {
+        Some(index) => {
+            let index = index as u32;
+            let mut donation = escrow.get_unchecked(index);
+            donation.amount_usdc += amount_usdc;
+            donation.claimable_until = claimable_until;
+            escrow.set(index, donation.clone());
+            donation
+        }
+        None => {
+            if escrow.len() >= MAX_ESCROWED_DONORS {
+                return Err(Error::EscrowFull);
+            }
+            let donation = EscrowedDonation {
+                donor: donor.clone(),
+                amount_usdc,
+                claimable_until,
+            };
+            escrow.push_back(donation.clone());
+            donation
+        }
+    };
+    write_escrow(env, beneficiary, &escrow);
+    add_total_escrowed(env, amount_usdc);
+    Ok(donation)
+}
+
+/// Takes the expired donations out of `beneficiary`'s escrow and returns
+/// their total.
+pub fn take_expired(env: &Env, beneficiary: &Address) -> i128 {
+    let now = env.ledger().timestamp();
+    let mut kept = vec![env];
+    let mut expired = 0;
+    for donation in read_escrow(env, beneficiary).iter() {
+        if donation.claimable_until >= now {
+            kept.push_back(donation);
+        } else {
+            expired += donation.amount_usdc;
+        }
+    }
+    write_escrow(env, beneficiary, &kept);
+    add_total_escrowed(env, -expired);
+    expired
+}
+
+/// Empties `beneficiary`'s escrow and returns what could still be claimed
+/// and what had expired, in that order.
+pub fn take_all(env: &Env, beneficiary: &Address) -> (i128, i128) {
+    let expired = take_expired(env, beneficiary);
+    let claimable: i128 = read_escrow(env, beneficiary)
+        .iter()
+        .map(|donation| donation.amount_usdc)
+        .sum();
+    write_escrow(env, beneficiary, &vec![env]);
+    add_total_escrowed(env, -claimable);
+    (claimable, expired)
+}
@@ -0,0 +1,132 @@
+//! Donation receipts for the donors' income-tax deduction.
+//!
+//! Every donation, whether made with `donate` or as the donated part of an
+//! investment, gets a numbered receipt. Receipts are also filed under the
+//! donor and the calendar year (UTC) they were made in, with a running
+//! total, so a yearly statement is a single read.
+
+use soroban_sdk::{contracttype, vec, Address, Env, Vec};
+
+use crate::ttl;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct DonationReceipt {
+    pub id: u64,
+    pub donor: Address,
+    pub amount_usdc: i128,
+    pub timestamp: u64,
+    /// Who a directed donation was made to.
+    pub beneficiary: Option<Address>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct DonationStatement {
+    pub donor: Address,
+    pub year: u32,
+    pub total_usdc: i128,
+    pub receipts: Vec<DonationReceipt>,
+}
+
+/// A donor's donations in one calendar year.
+#[derive(Clone)]
+#[contracttype]
+pub struct DonorYear {
+    pub total_usdc: i128,
+    pub receipts: Vec<u64>,
+}
+
+/// The receipt counter is instance storage; receipts and yearly totals are
+/// persistent entries of their own.
+#[derive(Clone)]
+#[contracttype]
+pub enum DonationKey {
+    NextReceipt,
+    Receipt(u64),
+    Year(Address, u32),
+}
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Calendar year (UTC) of a ledger timestamp.
+pub fn year_of(timestamp: u64) -> u32 {
+    // Days since 1970-01-01 to a proleptic Gregorian date, counting years
+    // from March so the leap day falls at the end.
+    let z = timestamp / SECONDS_PER_DAY + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let year = yoe + era * 400;
+    // March-based months 10 and 11 are January and February of the next year.
+    (if mp >= 10 { year + 1 } else { year }) as u32
+}
+
+/// Issues a receipt for `amount_usdc` donated by `donor` now.
+pub fn issue(env: &Env, donor: &Address, amount_usdc: i128, beneficiary: Option<Address>) -> DonationReceipt {
+    let id: u64 = env.storage().instance().get(&DonationKey::NextReceipt).unwrap_or(0);
+    env.storage().instance().set(&DonationKey::NextReceipt, &(id + 1));
+
+    let receipt = DonationReceipt {
+        id,
+        donor: donor.clone(),
+        amount_usdc,
+        timestamp: env.ledger().timestamp(),
+        beneficiary,
+    };
+    let key = DonationKey::Receipt(id);
+    env.storage().persistent().set(&key, &receipt);
+    ttl::extend_persistent(env, &key);
+
+    let year = year_of(receipt.timestamp);
+    let mut filed = read_year(env, donor, year);
+    filed.total_usdc += amount_usdc;
+    filed.receipts.push_back(id);
+    let key = DonationKey::Year(donor.clone(), year);
+    env.storage().persistent().set(&key, &filed);
+    ttl::extend_persistent(env, &key);
+
+    receipt
+}
+
+pub fn read_receipt(env: &Env, id: u64) -> Option<DonationReceipt> {
+    let key = DonationKey::Receipt(id);
+    let receipt = env.storage().persistent().get(&key);
+    if receipt.is_some() {
+        ttl::extend_persistent(env, &key);
+    }
+    receipt
+}
+
+fn read_year(env: &Env, donor: &Address, year: u32) -> DonorYear {
+    let key = DonationKey::Year(donor.clone(), year);
+    match env.storage().persistent().get(&key) {
+        Some(filed) => {
+            ttl::extend_persistent(env, &key);
+            filed
+        }
+        None => DonorYear {
+            total_usdc: 0,
+            receipts: vec![env],
+        },
+    }
+}
+
+/// Everything `donor` gave in `year`, receipt by receipt.
+pub fn statement(env: &Env, donor: &Address, year: u32) -> DonationStatement {
+    let filed = read_year(env, donor, year);
+    let mut receipts = vec![env];
+    for id in filed.receipts.iter() {
+        if let Some(receipt) = read_receipt(env, id) {
+            receipts.push_back(receipt);
+        }
+    }
+    DonationStatement {
+        donor: donor.clone(),
+        year,
+        total_usdc: filed.total_usdc,
+        receipts,
+    }
+}
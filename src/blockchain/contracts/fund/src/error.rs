@@ -0,0 +1,48 @@
+use soroban_sdk::contracterror;
+
+/// Failure codes returned by the fund contract.
+///
+/// The numeric values are part of the public interface: clients match on
+/// them through the `try_*` methods, so existing codes must never change.
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum Error {
+    NotInitialized = 1,
+    AlreadyInitialized = 2,
+    Unauthorized = 3,
+    InvalidAmount = 4,
+    InsufficientShares = 5,
+    InsufficientAllowance = 6,
+    InsufficientSeniorBalance = 7,
+    InsufficientSubordinatedBalance = 8,
+    InvestmentTooSmall = 9,
+    NoPosition = 10,
+    NoPendingAdmin = 11,
+    InvalidRole = 12,
+    InvalidExpirationLedger = 13,
+    InvalidAllocation = 14,
+    InvalidSwapPath = 15,
+    DeadlineExpired = 16,
+    SlippageExceeded = 17,
+    SwapFailed = 18,
+    SwapRequired = 19,
+    MigrationPending = 20,
+    Paused = 21,
+    NotPaused = 22,
+    RequestNotFound = 23,
+    InvalidLockup = 24,
+    Locked = 25,
+    ReceiptNotFound = 26,
+    NothingToClaim = 27,
+    InvalidSubsidy = 28,
+    InvalidCoverage = 29,
+    CohortNotFound = 30,
+    InvalidCampaign = 31,
+    CampaignNotFound = 32,
+    CampaignNotEnded = 33,
+    CampaignClosed = 34,
+    TooManyCampaigns = 35,
+    EscrowFull = 36,
+    InvalidRecipient = 37,
+}
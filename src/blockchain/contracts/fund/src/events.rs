@@ -0,0 +1,258 @@
+//! Events published by the fund.
+//!
+//! Every state change publishes one event of the fund's own. Its topics are
+//! `(CONTRACT, action, subject)`: the contract family, what happened, and the
+//! account it happened to or that made it happen, so indexers can filter on
+//! any of them. The data is `(version, payload)` with one of the typed
+//! payloads below; the action name says which. Each action's payload is
+//! versioned on its own, so an indexer only has to handle the actions whose
+//! version moved (see `version`).
+//!
+//! Share movements additionally publish the standard SEP-41 token events
+//! (see `share`).
+//!
+//! | action                   | subject     | payload               | v |
+//! |--------------------------|-------------|-----------------------|---|
+//! | `initialized`            | admin       | `Initialized`         | 1 |
+//! | `invested`               | investor    | `Invested`            | 1 |
+//! | `donated`                | donor       | `Donated`             | 2 |
+//! | `receipt_issued`         | donor       | `DonationReceipt`     | 2 |
+//! | `donation_matched`       | donor       | `DonationMatched`     | 1 |
+//! | `campaign_created`       | sponsor     | `Campaign`            | 1 |
+//! | `campaign_closed`        | sponsor     | `CampaignClosed`      | 1 |
+//! | `donation_claimed`       | beneficiary | `DonationClaimed`     | 1 |
+//! | `donation_released`      | beneficiary | `DonationReleased`    | 1 |
+//! | `guarantee_donated`      | donor       | `GuaranteeDonated`    | 1 |
+//! | `guarantee_claimed`      | farmer      | `GuaranteeClaim`      | 1 |
+//! | `guarantee_recovered`    | farmer      | `GuaranteeRecovered`  | 1 |
+//! | `redeemed`               | investor    | `Redemption`          | 3 |
+//! | `emergency_withdrawn`    | investor    | `Redemption`          | 3 |
+//! | `redemption_requested`   | investor    | `RedemptionRequested` | 2 |
+//! | `redemption_cancelled`   | investor    | `RedemptionRequest`   | 1 |
+//! | `redemption_filled`      | investor    | `RedemptionFilled`    | 2 |
+//! | `unstaked`               | operator    | `Unstaked`            | 1 |
+//! | `harvested`              | fund        | `Harvested`           | 1 |
+//! | `capital_drawn`          | farmer      | `CapitalDrawn`        | 1 |
+//! | `capital_repaid`         | farmer      | `CapitalRepaid`       | 1 |
+//! | `interest_subsidized`    | farmer      | `InterestSubsidized`  | 1 |
+//! | `loss_absorbed`          | farmer      | `LossStep`            | 1 |
+//! | `loss_recovered`         | farmer      | `LossStep`            | 1 |
+//! | `addresses_updated`      | admin       | `ContractAddresses`   | 1 |
+//! | `allocation_updated`     | admin       | `Allocation`          | 1 |
+//! | `payment_contract_set`   | admin       | payment `Address`     | 1 |
+//! | `notice_period_updated`  | admin       | seconds `u64`         | 1 |
+//! | `lockup_updated`         | admin       | `Lockup`              | 1 |
+//! | `claim_window_updated`   | admin       | seconds `u64`         | 1 |
+//! | `subsidy_updated`        | admin       | `Subsidy`             | 1 |
+//! | `cohort_updated`         | admin       | `CohortUpdated`       | 1 |
+//! | `cohort_assigned`        | farmer      | `Option<Symbol>`      | 1 |
+//! | `role_granted`           | account     | `RoleChanged`         | 1 |
+//! | `role_revoked`           | account     | `RoleChanged`         | 1 |
+//! | `admin_transfer_started` | admin       | new admin `Address`   | 1 |
+//! | `admin_transferred`      | new admin   | old admin `Address`   | 1 |
+//! | `pause_updated`          | pauser      | `PauseUpdated`        | 1 |
+//! | `upgraded`               | admin       | wasm hash `BytesN`    | 1 |
+//! | `migrated`               | admin       | `Migrated`            | 1 |
+
+use soroban_sdk::{contracttype, symbol_short, Address, Env, IntoVal, Symbol, Val};
+
+use crate::{Allocation, ContractAddresses, PauseScope, Redemption, RedemptionRequest, Role, Tranche};
+
+pub const CONTRACT: Symbol = symbol_short!("fund");
+
+/// Version of `action`'s payload, bumped whenever it changes shape or
+/// meaning. Actions not listed are on version 1.
+///
+/// - `redeemed`, `emergency_withdrawn`: 2 reports the principal and yield
+///   paid out, 3 the early-exit fee charged.
+/// - `redemption_requested`, `redemption_filled`: 2 reports the early-exit
+///   fee.
+/// - `donated`, `receipt_issued`: 2 names the beneficiary of directed
+///   donations.
+pub fn version(action: &str) -> u32 {
+    match action {
+        "redeemed" | "emergency_withdrawn" => 3,
+        "redemption_requested" | "redemption_filled" | "donated" | "receipt_issued" => 2,
+        _ => 1,
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct Initialized {
+    pub addresses: ContractAddresses,
+    pub allocation: Allocation,
+}
+
+/// USDC amounts are what each part of the fund received.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct Invested {
+    pub amount_usdc: i128,
+    pub shares: i128,
+    pub senior: i128,
+    pub subordinated: i128,
+    pub subordinated_xlm: i128,
+    pub reserve: i128,
+    pub donation: i128,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct Donated {
+    pub amount_usdc: i128,
+    pub beneficiary: Option<Address>,
+}
+
+/// `sponsor`'s match of the subject's donation in `campaign`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct DonationMatched {
+    pub campaign: u64,
+    pub sponsor: Address,
+    pub amount_usdc: i128,
+}
+
+/// `refunded` is the match money the sponsor got back.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct CampaignClosed {
+    pub id: u64,
+    pub matched: i128,
+    pub refunded: i128,
+}
+
+/// A directed donation paid out to `claimant` for the event's subject.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct DonationClaimed {
+    pub claimant: Address,
+    pub amount_usdc: i128,
+}
+
+/// Directed donations moved to the general pool after their claim window.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct DonationReleased {
+    pub amount_usdc: i128,
+}
+
+/// `pool` is the guarantee pool's balance afterwards.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct GuaranteeDonated {
+    pub amount_usdc: i128,
+    pub pool: i128,
+}
+
+/// Part of a recovery that went back into the guarantee pool.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct GuaranteeRecovered {
+    pub amount_usdc: i128,
+    pub pool: i128,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct CohortUpdated {
+    pub cohort: Symbol,
+    pub loan_cap: i128,
+    pub cohort_cap: i128,
+}
+
+/// `proceeds` is in `token`, which is USDC when the XLM was swapped.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct Unstaked {
+    pub amount_xlm: i128,
+    pub token: Address,
+    pub proceeds: i128,
+    pub recipient: Option<Address>,
+}
+
+/// `request` holds the shares left after the early-exit fee.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct RedemptionRequested {
+    pub request: RedemptionRequest,
+    pub early_exit_fee: i128,
+}
+
+/// One fill of queued request `id`; a partial fill leaves the rest queued.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct RedemptionFilled {
+    pub id: u64,
+    pub redemption: Redemption,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct Harvested {
+    pub interest_xlm: i128,
+    pub interest_usdc: i128,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct CapitalDrawn {
+    pub amount_usdc: i128,
+    pub loans_receivable: i128,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct CapitalRepaid {
+    pub principal: i128,
+    pub interest: i128,
+    pub loans_receivable: i128,
+}
+
+/// `pool` is what is left in the donation pool afterwards.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct InterestSubsidized {
+    pub interest: i128,
+    pub subsidy: i128,
+    pub pool: i128,
+}
+
+/// One tranche's part in a write-off or recovery; `balance` is the
+/// tranche's book value afterwards.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct LossStep {
+    pub tranche: Tranche,
+    pub amount: i128,
+    pub balance: i128,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct RoleChanged {
+    pub role: Role,
+    pub admin: Address,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct PauseUpdated {
+    pub scope: PauseScope,
+    pub paused: bool,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct Migrated {
+    pub version: u32,
+    pub remaining: u32,
+}
+
+pub fn publish<D: IntoVal<Env, Val>>(env: &Env, action: &str, subject: &Address, payload: D) {
+    let payload: Val = payload.into_val(env);
+    env.events().publish(
+        (CONTRACT, Symbol::new(env, action), subject.clone()),
+        (version(action), payload),
+    );
+}
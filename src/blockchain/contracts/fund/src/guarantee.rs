@@ -0,0 +1,223 @@
+//! First-loss guarantee backed by donations.
+//!
+//! Donors can fund a guarantee pool, kept apart from both the investors'
+//! tranches and the general donation pool, that covers the first losses on
+//! written-off loans of farmers in vulnerable segments. Admins set up
+//! cohorts, each with a cap on what a single loan can claim and a cap on
+//! what the whole cohort can claim, and assign farmers to them. Loans of
+//! farmers outside any cohort are not covered.
+//!
+//! When the payment contract writes off a covered loan, the pool pays the
+//! loss up to both caps before the waterfall sees the rest. Recoveries on
+//! written-off loans refill the pool once the tranches are whole again.
+
+use soroban_sdk::{contracttype, vec, Address, Env, Symbol, Vec};
+
+use crate::{ttl, Error};
+
+/// A cohort's caps and what it has claimed so far.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct GuaranteeCohort {
+    pub loan_cap: i128,
+    pub cohort_cap: i128,
+    pub used: i128,
+    pub claims: u32,
+}
+
+/// How much of a cohort's coverage is used and how much is left, which is
+/// also limited by what the pool holds.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct CohortCoverage {
+    pub cohort: Symbol,
+    pub loan_cap: i128,
+    pub cohort_cap: i128,
+    pub used: i128,
+    pub remaining: i128,
+    pub claims: u32,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct GuaranteeClaim {
+    pub farmer: Address,
+    pub cohort: Symbol,
+    pub loss: i128,
+    pub covered: i128,
+    pub timestamp: u64,
+}
+
+/// The pool's balance and what it paid out that hasn't been recovered are
+/// instance storage; cohorts, memberships and each farmer's claims are
+/// persistent entries of their own.
+#[derive(Clone)]
+#[contracttype]
+pub enum GuaranteeKey {
+    Pool,
+    Unrecovered,
+    Cohort(Symbol),
+    Member(Address),
+    Claims(Address),
+}
+
+/// USDC in the guarantee pool, which belongs to neither the investors nor
+/// the general pool.
+pub fn pool(env: &Env) -> i128 {
+    env.storage().instance().get(&GuaranteeKey::Pool).unwrap_or(0)
+}
+
+fn add_pool(env: &Env, amount: i128) -> i128 {
+    let pool = pool(env) + amount;
+    env.storage().instance().set(&GuaranteeKey::Pool, &pool);
+    pool
+}
+
+/// Claims paid out that recoveries haven't refilled yet.
+pub fn unrecovered(env: &Env) -> i128 {
+    env.storage().instance().get(&GuaranteeKey::Unrecovered).unwrap_or(0)
+}
+
+fn add_unrecovered(env: &Env, amount: i128) {
+    env.storage()
+        .instance()
+        .set(&GuaranteeKey::Unrecovered, &(unrecovered(env) + amount));
+}
+
+/// Adds a donation of `amount_usdc` to the pool. Returns the new balance.
+pub fn donate(env: &Env, amount_usdc: i128) -> i128 {
+    add_pool(env, amount_usdc)
+}
+
+/// Puts back up to `amount_usdc` of what the pool paid out, out of a
+/// recovery the fund has received. Returns the amount put back.
+pub fn refill(env: &Env, amount_usdc: i128) -> i128 {
+    let refill = amount_usdc.min(unrecovered(env)).max(0);
+    add_unrecovered(env, -refill);
+    add_pool(env, refill);
+    refill
+}
+
+pub fn read_cohort(env: &Env, cohort: &Symbol) -> Option<GuaranteeCohort> {
+    let key = GuaranteeKey::Cohort(cohort.clone());
+    let record = env.storage().persistent().get(&key);
+    if record.is_some() {
+        ttl::extend_persistent(env, &key);
+    }
+    record
+}
+
+fn write_cohort(env: &Env, cohort: &Symbol, record: &GuaranteeCohort) {
+    let key = GuaranteeKey::Cohort(cohort.clone());
+    env.storage().persistent().set(&key, record);
+    ttl::extend_persistent(env, &key);
+}
+
+/// Sets a cohort's caps, creating it if needed. What it has already
+/// claimed stays on record, so a lower cap can leave nothing to claim.
+pub fn set_cohort(env: &Env, cohort: &Symbol, loan_cap: i128, cohort_cap: i128) -> Result<(), Error> {
+    if loan_cap < 0 || cohort_cap < 0 {
+        return Err(Error::InvalidCoverage);
+    }
+    let record = match read_cohort(env, cohort) {
+        Some(record) => GuaranteeCohort {
+            loan_cap,
+            cohort_cap,
+            ..record
+        },
+        None => GuaranteeCohort {
+            loan_cap,
+            cohort_cap,
+            used: 0,
+            claims: 0,
+        },
+    };
+    write_cohort(env, cohort, &record);
+    Ok(())
+}
+
+pub fn coverage(env: &Env, cohort: &Symbol) -> Option<CohortCoverage> {
+    let record = read_cohort(env, cohort)?;
+    Some(CohortCoverage {
+        cohort: cohort.clone(),
+        loan_cap: record.loan_cap,
+        cohort_cap: record.cohort_cap,
+        used: record.used,
+        remaining: (record.cohort_cap - record.used).min(pool(env)).max(0),
+        claims: record.claims,
+    })
+}
+
+pub fn read_member(env: &Env, farmer: &Address) -> Option<Symbol> {
+    let key = GuaranteeKey::Member(farmer.clone());
+    let cohort = env.storage().persistent().get(&key);
+    if cohort.is_some() {
+        ttl::extend_persistent(env, &key);
+    }
+    cohort
+}
+
+/// Puts `farmer` in `cohort`, or takes them out of any with `None`.
+pub fn set_member(env: &Env, farmer: &Address, cohort: &Option<Symbol>) -> Result<(), Error> {
+    let key = GuaranteeKey::Member(farmer.clone());
+    match cohort {
+        Some(cohort) => {
+            if read_cohort(env, cohort).is_none() {
+                return Err(Error::CohortNotFound);
+            }
+            env.storage().persistent().set(&key, cohort);
+            ttl::extend_persistent(env, &key);
+        }
+        None => env.storage().persistent().remove(&key),
+    }
+    Ok(())
+}
+
+/// Claims made on `farmer`'s written-off loans, oldest first.
+pub fn read_claims(env: &Env, farmer: &Address) -> Vec<GuaranteeClaim> {
+    let key = GuaranteeKey::Claims(farmer.clone());
+    match env.storage().persistent().get(&key) {
+        Some(claims) => {
+            ttl::extend_persistent(env, &key);
+            claims
+        }
+        None => vec![env],
+    }
+}
+
+/// Pays what the pool covers of a `loss` on `farmer`'s written-off loan
+/// and records the claim. Returns `None` when the farmer isn't covered or
+/// nothing is left to cover the loss with.
+pub fn claim(env: &Env, farmer: &Address, loss: i128) -> Option<GuaranteeClaim> {
+    let cohort = read_member(env, farmer)?;
+    let mut record = read_cohort(env, &cohort)?;
+
+    let covered = loss
+        .min(record.loan_cap)
+        .min(record.cohort_cap - record.used)
+        .min(pool(env))
+        .max(0);
+    if covered == 0 {
+        return None;
+    }
+
+    record.used += covered;
+    record.claims += 1;
+    write_cohort(env, &cohort, &record);
+    add_pool(env, -covered);
+    add_unrecovered(env, covered);
+
+    let claim = GuaranteeClaim {
+        farmer: farmer.clone(),
+        cohort,
+        loss,
+        covered,
+        timestamp: env.ledger().timestamp(),
+    };
+    let mut claims = read_claims(env, farmer);
+    claims.push_back(claim.clone());
+    let key = GuaranteeKey::Claims(farmer.clone());
+    env.storage().persistent().set(&key, &claims);
+    ttl::extend_persistent(env, &key);
+    Some(claim)
+}
@@ -1,29 +1,77 @@
 #![no_std]
 use soroban_sdk::{
-    contract, contractimpl, contracttype, Address, Env, Symbol, Map, vec, 
-    String, symbol_short, IntoVal,
+    contract, contractimpl, contracttype, Address, BytesN, Env, Symbol, Vec, vec, 
+    symbol_short, IntoVal,
 };
 
+mod access;
+mod allocation;
+mod blend;
+mod campaign;
+mod directed;
+mod donation;
+mod error;
+mod events;
+mod guarantee;
+mod lots;
+mod migration;
+mod nav;
+mod pause;
+mod position;
+mod queue;
+mod router;
+mod share;
+mod subsidy;
+mod ttl;
+mod waterfall;
+
+pub use access::Role;
+pub use allocation::Allocation;
+pub use campaign::{Campaign, CampaignProgress};
+pub use directed::EscrowedDonation;
+pub use donation::{DonationReceipt, DonationStatement};
+pub use error::Error;
+pub use guarantee::{CohortCoverage, GuaranteeClaim};
+pub use lots::{Lockup, Lot};
+pub use pause::{PausableFunction, PauseScope};
+pub use position::Redemption;
+pub use queue::{ExpectedFill, RedemptionRequest};
+pub use router::SwapParams;
+pub use subsidy::Subsidy;
+pub use waterfall::{LossAllocation, Tranche};
+
+#[cfg(test)]
+mod test;
+
 const ADDRS_KEY: Symbol = symbol_short!("ADDRS");
 const STATE_KEY: Symbol = symbol_short!("STATE");
+const ALLOC_KEY: Symbol = symbol_short!("ALLOC");
+const PAYMENT_KEY: Symbol = symbol_short!("PAYMENT");
 
 
-#[derive(Clone)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 #[contracttype]
 pub struct ContractAddresses {
     pub soroswap_router: Address,
+    /// Blend lending pool holding the subordinated tranche.
     pub blend_comet: Address,
     pub usdc_token: Address,
     pub xlm_token: Address,
 }
 
+/// Fund-wide aggregates, kept in instance storage. Each holder's share
+/// balance is a persistent entry of its own (see `share`).
 #[derive(Clone)]
 #[contracttype]
 pub struct FundState {
-    pub shares: Map<Address, i128>, 
+    pub total_shares: i128,
     pub total_senior: i128,         
     pub total_subordinated: i128,   
+    pub subordinated_xlm: i128,
     pub total_fund: i128,           
+    pub total_reserve: i128,
+    pub loans_receivable: i128,
+    pub total_yield: i128,
 }
 
 #[contract]
@@ -32,87 +80,311 @@ pub struct FundContract;
 #[contractimpl]
 impl FundContract {
 
-    pub fn init(env: Env, router: Address, comet: Address, usdc: Address, xlm: Address) {
-
-        env.storage().persistent().set(
-            &ADDRS_KEY,
-            &ContractAddresses {
-                soroswap_router: router,
-                blend_comet: comet,
-                usdc_token: usdc,
-                xlm_token: xlm,
-            },
-        );
+    /// Sets the fund up. A deployment of the first version never had an
+    /// admin, so this is also how one is taken over: its balances stay in
+    /// the legacy blob for the new admin to `migrate`.
+    pub fn init(
+        env: Env,
+        admin: Address,
+        router: Address,
+        comet: Address,
+        usdc: Address,
+        xlm: Address,
+        allocation: Allocation,
+    ) -> Result<(), Error> {
+        if access::is_initialized(&env) {
+            return Err(Error::AlreadyInitialized);
+        }
+        allocation::validate(&allocation)?;
 
-        let state = FundState {
-            shares: Map::new(&env),
-            total_senior: 0,
-            total_subordinated: 0,
-            total_fund: 0,
+        let addresses = ContractAddresses {
+            soroswap_router: router,
+            blend_comet: comet,
+            usdc_token: usdc,
+            xlm_token: xlm,
         };
-        env.storage().persistent().set(&STATE_KEY, &state);
+        access::write_admin(&env, &admin);
+        save_allocation(&env, &allocation);
+        save_addresses(&env, &addresses);
+
+        if !migration::has_legacy_state(&env) {
+            let state = FundState {
+                total_shares: 0,
+                total_senior: 0,
+                total_subordinated: 0,
+                subordinated_xlm: 0,
+                total_fund: 0,
+                total_reserve: 0,
+                loans_receivable: 0,
+                total_yield: 0,
+            };
+            save_state(&env, &state);
+            migration::write_version(&env, migration::SCHEMA_VERSION);
+        }
+
+        events::publish(&env, "initialized", &admin, events::Initialized { addresses, allocation });
+        Ok(())
     }
 
-    pub fn invest(env: Env, investor: Address, amount_usdc: i128) {
-        let mut state: FundState = env.storage().persistent()
-            .get(&STATE_KEY)
-            .unwrap_or_else(|| panic!("Contract not initialized"));
+    /// Takes `amount_usdc` from the investor and mints fund shares for it.
+    ///
+    /// The subordinated part is swapped to XLM through Soroswap within the
+    /// limits in `swap` and supplied to Blend.
+    pub fn invest(env: Env, investor: Address, amount_usdc: i128, swap: SwapParams) -> Result<(), Error> {
+        investor.require_auth();
+        pause::require_not_paused(&env, PausableFunction::Invest)?;
+
+        if amount_usdc <= 0 {
+            return Err(Error::InvalidAmount);
+        }
+
+        let mut state = load_state(&env)?;
+        let addrs = load_addresses(&env)?;
 
-        let addrs: ContractAddresses = env.storage().persistent()
-            .get(&ADDRS_KEY)
-            .unwrap_or_else(|| panic!("Addresses not set"));
+        env.invoke_contract::<()>(
+            &addrs.usdc_token,
+            &Symbol::new(&env, "transfer"),
+            vec![
+                &env,
+                investor.clone().into_val(&env),
+                env.current_contract_address().into_val(&env),
+                amount_usdc.into_val(&env),
+            ],
+        );
+
+        let split = allocation::split(&load_allocation(&env)?, amount_usdc);
+
+        // The donated part is not invested, so it buys no shares. The rest is
+        // priced before it lands, so the investor buys in at the current NAV.
+        let new_shares = nav::shares_for_deposit(&state, amount_usdc - split.donation);
+        if new_shares <= 0 {
+            return Err(Error::InvestmentTooSmall);
+        }
 
-        let senior_part = amount_usdc * 82 / 100;
-        let xlm_part = amount_usdc * 15 / 100;
-        let fund_part = amount_usdc * 3 / 100;
+        let senior_part = split.senior;
+        let xlm_part = split.subordinated;
+        let mut xlm_received = 0;
+
+        if xlm_part > 0 {
+            xlm_received = router::swap_exact_in(
+                &env,
+                &addrs.soroswap_router,
+                &addrs.usdc_token,
+                &addrs.xlm_token,
+                xlm_part,
+                &swap,
+            )?;
+
+            blend::supply_collateral(&env, &addrs.blend_comet, &addrs.xlm_token, xlm_received);
+
+            state.subordinated_xlm += xlm_received;
+        }
 
         state.total_senior += senior_part;
         state.total_subordinated += xlm_part;
-        state.total_fund += fund_part;
+        state.total_reserve += split.reserve;
+        state.total_fund += split.donation;
+
+        share::mint(&env, &mut state, &investor, new_shares)?;
+        position::add_principal(&env, &investor, amount_usdc - split.donation);
+        lots::open(&env, &investor, new_shares);
+
+        save_state(&env, &state);
+
+        events::publish(
+            &env,
+            "invested",
+            &investor,
+            events::Invested {
+                amount_usdc,
+                shares: new_shares,
+                senior: senior_part,
+                subordinated: xlm_part,
+                subordinated_xlm: xlm_received,
+                reserve: split.reserve,
+                donation: split.donation,
+            },
+        );
+        if split.donation > 0 {
+            issue_receipt(&env, &investor, split.donation, None);
+        }
+        Ok(())
+    }
+
+    /// Takes a donation from `donor`. It goes to the general pool, or with
+    /// a `beneficiary` into escrow for them (see `directed`). Running
+    /// campaigns match it to the same place (see `campaign`).
+    pub fn donate(env: Env, donor: Address, amount_usdc: i128, beneficiary: Option<Address>) -> Result<(), Error> {
+        donor.require_auth();
+        pause::require_not_paused(&env, PausableFunction::Donate)?;
 
-        let invested = state.shares.get(investor.clone()).unwrap_or(0);
-        state.shares.set(investor.clone(), invested + amount_usdc);
+        if amount_usdc <= 0 {
+            return Err(Error::InvalidAmount);
+        }
 
-        env.storage().persistent().set(&STATE_KEY, &state);
+        let mut state = load_state(&env)?;
+        let matches = campaign::match_donation(&env, amount_usdc);
+        
+        match &beneficiary {
+            Some(beneficiary) => {
+                directed::deposit(&env, &donor, beneficiary, amount_usdc)?;
+                for (campaign, matched) in matches.iter() {
+                    directed::deposit(&env, &campaign.sponsor, beneficiary, matched)?;
+                }
+            }
+            None => {
+                state.total_fund += amount_usdc;
+                for (_, matched) in matches.iter() {
+                    state.total_fund += matched;
+                }
+                save_state(&env, &state);
+            }
+        }
+
+        let addrs = load_addresses(&env)?;
 
         env.invoke_contract::<()>(
-            &addrs.soroswap_router,
-            &Symbol::new(&env, "swap_exact_tokens_for_tokens"),
+            &addrs.usdc_token,
+            &Symbol::new(&env, "transfer"),
             vec![
                 &env,
-                addrs.usdc_token.into_val(&env),
-                senior_part.into_val(&env),
-                addrs.xlm_token.into_val(&env),
-                investor.clone().into_val(&env),
+                donor.clone().into_val(&env),
+                env.current_contract_address().into_val(&env),
+                amount_usdc.into_val(&env),
             ],
-        );        
+        );
+
+        events::publish(
+            &env,
+            "donated",
+            &donor,
+            events::Donated {
+                amount_usdc,
+                beneficiary: beneficiary.clone(),
+            },
+        );
+        issue_receipt(&env, &donor, amount_usdc, beneficiary);
+        for (campaign, matched) in matches.iter() {
+            events::publish(
+                &env,
+                "donation_matched",
+                &donor,
+                events::DonationMatched {
+                    campaign: campaign.id,
+                    sponsor: campaign.sponsor,
+                    amount_usdc: matched,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    /// Opens a campaign in which `sponsor` matches donations made between
+    /// `starts_at` and `ends_at` at `match_bps` of their amount, up to
+    /// `cap`, which it escrows with the fund now. An admin approves it by
+    /// signing too. Returns its id.
+    pub fn create_campaign(
+        env: Env,
+        admin: Address,
+        sponsor: Address,
+        match_bps: u32,
+        cap: i128,
+        starts_at: u64,
+        ends_at: u64,
+    ) -> Result<u64, Error> {
+        access::require_role(&env, &admin, Role::Admin)?;
+        sponsor.require_auth();
+        pause::require_not_paused(&env, PausableFunction::Donate)?;
+
+        let campaign = campaign::create(&env, &sponsor, match_bps, cap, starts_at, ends_at)?;
+        let addrs = load_addresses(&env)?;
 
         env.invoke_contract::<()>(
-            &addrs.blend_comet,
-            &Symbol::new(&env, "supply"),
+            &addrs.usdc_token,
+            &Symbol::new(&env, "transfer"),
             vec![
-                &env, 
-                addrs.xlm_token.into_val(&env), 
-                xlm_part.into_val(&env)
+                &env,
+                sponsor.clone().into_val(&env),
+                env.current_contract_address().into_val(&env),
+                cap.into_val(&env),
             ],
         );
+
+        let id = campaign.id;
+        events::publish(&env, "campaign_created", &sponsor, campaign);
+        Ok(id)
     }
 
-    pub fn donate(env: Env, donor: Address, amount_usdc: i128) {
-        donor.require_auth();
+    /// Closes a campaign that has ended and refunds the match money it
+    /// didn't use to the sponsor, who gets a receipt for what it matched.
+    /// Anyone can call it. Returns the refund.
+    pub fn close_campaign(env: Env, id: u64) -> Result<i128, Error> {
+        let (campaign, refund) = campaign::close(&env, id)?;
 
-        let mut state: FundState = env.storage().persistent()
-            .get(&STATE_KEY)
-            .unwrap_or_else(|| panic!("Contract not initialized"));
-        
-        state.total_fund += amount_usdc;
+        if refund > 0 {
+            let addrs = load_addresses(&env)?;
 
-        env.storage().persistent().set(&STATE_KEY, &state);
+            env.invoke_contract::<()>(
+                &addrs.usdc_token,
+                &Symbol::new(&env, "transfer"),
+                vec![
+                    &env,
+                    env.current_contract_address().into_val(&env),
+                    campaign.sponsor.clone().into_val(&env),
+                    refund.into_val(&env),
+                ],
+            );
+        }
 
-        let addrs: ContractAddresses = env.storage().persistent()
-            .get(&ADDRS_KEY)
-            .unwrap_or_else(|| panic!("Addresses not set"));
-        
+        events::publish(
+            &env,
+            "campaign_closed",
+            &campaign.sponsor,
+            events::CampaignClosed {
+                id,
+                matched: campaign.matched,
+                refunded: refund,
+            },
+        );
+        if campaign.matched > 0 {
+            issue_receipt(&env, &campaign.sponsor, campaign.matched, None);
+        }
+        Ok(refund)
+    }
+
+    pub fn get_campaign(env: Env, id: u64) -> Result<Campaign, Error> {
+        campaign::read(&env, id).ok_or(Error::CampaignNotFound)
+    }
+
+    pub fn get_campaign_progress(env: Env, id: u64) -> Result<CampaignProgress, Error> {
+        let campaign = campaign::read(&env, id).ok_or(Error::CampaignNotFound)?;
+        Ok(campaign::progress(&env, &campaign))
+    }
+
+    /// Campaigns that haven't been closed yet, oldest first. There are at
+    /// most `MAX_OPEN_CAMPAIGNS` of them.
+    pub fn get_open_campaigns(env: Env) -> Vec<Campaign> {
+        let mut campaigns = vec![&env];
+        for id in campaign::open_ids(&env).iter() {
+            if let Some(campaign) = campaign::read(&env, id) {
+                campaigns.push_back(campaign);
+            }
+        }
+        campaigns
+    }
+
+    /// Takes a donation from `donor` for the first-loss guarantee pool
+    /// (see `guarantee`).
+    pub fn donate_to_guarantee(env: Env, donor: Address, amount_usdc: i128) -> Result<(), Error> {
+        donor.require_auth();
+        pause::require_not_paused(&env, PausableFunction::Donate)?;
+
+        if amount_usdc <= 0 {
+            return Err(Error::InvalidAmount);
+        }
+
+        let addrs = load_addresses(&env)?;
+        let pool = guarantee::donate(&env, amount_usdc);
 
         env.invoke_contract::<()>(
             &addrs.usdc_token,
@@ -124,71 +396,1140 @@ impl FundContract {
                 amount_usdc.into_val(&env),
             ],
         );
+
+        events::publish(&env, "guarantee_donated", &donor, events::GuaranteeDonated { amount_usdc, pool });
+        issue_receipt(&env, &donor, amount_usdc, None);
+        Ok(())
     }
 
-    pub fn unstake_subordinated(env: Env, amount: i128, _recipient: Address) {
-        let state: FundState = env.storage().persistent()
-            .get(&STATE_KEY)
-            .unwrap_or_else(|| panic!("Contract not initialized"));
+    /// Pays out the donations held for `beneficiary` to `claimant`, which
+    /// is either the beneficiary itself or a disburser paying out for it.
+    /// Donations past their claim window go to the general pool instead.
+    /// Returns the amount paid out.
+    pub fn claim_donations(env: Env, claimant: Address, beneficiary: Address) -> Result<i128, Error> {
+        if claimant == beneficiary {
+            claimant.require_auth();
+        } else {
+            access::require_role(&env, &claimant, Role::Disburser)?;
+        }
 
-        if state.total_subordinated < amount {
-            panic!("Saldo insuficiente para unstake");
+        let mut state = load_state(&env)?;
+        let (claimable, expired) = directed::take_all(&env, &beneficiary);
+        release_to_pool(&env, &mut state, &beneficiary, expired);
+        if claimable == 0 {
+            return Err(Error::NothingToClaim);
         }
 
-        let addrs: ContractAddresses = env.storage().persistent()
-            .get(&ADDRS_KEY)
-            .unwrap_or_else(|| panic!("Addresses not set"));
+        let addrs = load_addresses(&env)?;
 
         env.invoke_contract::<()>(
-            &addrs.blend_comet,
-            &Symbol::new(&env, "withdraw"),
+            &addrs.usdc_token,
+            &Symbol::new(&env, "transfer"),
+            vec![
+                &env,
+                env.current_contract_address().into_val(&env),
+                claimant.clone().into_val(&env),
+                claimable.into_val(&env),
+            ],
+        );
+
+        events::publish(
+            &env,
+            "donation_claimed",
+            &beneficiary,
+            events::DonationClaimed {
+                claimant,
+                amount_usdc: claimable,
+            },
+        );
+        Ok(claimable)
+    }
+
+    /// Moves `beneficiary`'s donations that are past their claim window to
+    /// the general pool. Anyone can call it. Returns the amount moved.
+    pub fn release_expired_donations(env: Env, beneficiary: Address) -> Result<i128, Error> {
+        let mut state = load_state(&env)?;
+        let expired = directed::take_expired(&env, &beneficiary);
+        release_to_pool(&env, &mut state, &beneficiary, expired);
+        Ok(expired)
+    }
+
+    /// Donations held for `beneficiary`, one entry per donor.
+    pub fn get_escrow(env: Env, beneficiary: Address) -> Vec<EscrowedDonation> {
+        directed::read_escrow(&env, &beneficiary)
+    }
+
+    pub fn set_claim_window(env: Env, admin: Address, seconds: u64) -> Result<(), Error> {
+        access::require_role(&env, &admin, Role::Admin)?;
+
+        directed::set_claim_window(&env, seconds);
+
+        events::publish(&env, "claim_window_updated", &admin, seconds);
+        Ok(())
+    }
+
+    pub fn get_claim_window(env: Env) -> u64 {
+        directed::claim_window(&env)
+    }
+
+    pub fn get_donation_receipt(env: Env, id: u64) -> Result<DonationReceipt, Error> {
+        donation::read_receipt(&env, id).ok_or(Error::ReceiptNotFound)
+    }
+
+    /// Every donation `donor` made in calendar year `year` (UTC), with the
+    /// total to deduct.
+    pub fn get_donation_statement(env: Env, donor: Address, year: u32) -> DonationStatement {
+        donation::statement(&env, &donor, year)
+    }
+
+    /// Pulls `amount_xlm` of the subordinated tranche out of Blend.
+    ///
+    /// With `swap` the XLM is sold for USDC first. The proceeds go to
+    /// `recipient`, or back into the senior tranche when there is none; the
+    /// senior tranche only holds USDC, so that needs a swap. The tranche's
+    /// cost basis shrinks pro rata. XLM the senior tranche has a claim on
+    /// (see `waterfall`) can only go back into senior, which settles the
+    /// claim. Returns the amount delivered.
+    pub fn unstake_subordinated(
+        env: Env,
+        operator: Address,
+        amount_xlm: i128,
+        recipient: Option<Address>,
+        swap: Option<SwapParams>,
+    ) -> Result<i128, Error> {
+        access::require_role(&env, &operator, Role::Operator)?;
+
+        if amount_xlm <= 0 {
+            return Err(Error::InvalidAmount);
+        }
+        if recipient.is_none() && swap.is_none() {
+            return Err(Error::SwapRequired);
+        }
+
+        let mut state = load_state(&env)?;
+
+        if state.subordinated_xlm < amount_xlm {
+            return Err(Error::InsufficientSubordinatedBalance);
+        }
+        // XLM that senior has a claim on can only go to senior.
+        let cost_basis = state.total_subordinated * amount_xlm / state.subordinated_xlm;
+        if recipient.is_some() && cost_basis > waterfall::free_subordinated(&env, &state) {
+            return Err(Error::InsufficientSubordinatedBalance);
+        }
+
+        let addrs = load_addresses(&env)?;
+        let fund = env.current_contract_address();
+
+        blend::withdraw_collateral(&env, &addrs.blend_comet, &addrs.xlm_token, amount_xlm, &fund);
+
+        let (token, proceeds) = match &swap {
+            Some(swap) => {
+                let usdc = router::swap_exact_in(
+                    &env,
+                    &addrs.soroswap_router,
+                    &addrs.xlm_token,
+                    &addrs.usdc_token,
+                    amount_xlm,
+                    swap,
+                )?;
+                (addrs.usdc_token.clone(), usdc)
+            }
+            None => (addrs.xlm_token.clone(), amount_xlm),
+        };
+
+        state.subordinated_xlm -= amount_xlm;
+        state.total_subordinated -= cost_basis;
+
+        if recipient.is_none() {
+            state.total_senior += proceeds;
+            waterfall::settle(&env, cost_basis);
+        }
+        save_state(&env, &state);
+
+        if let Some(recipient) = &recipient {
+            env.invoke_contract::<()>(
+                &token,
+                &Symbol::new(&env, "transfer"),
+                vec![
+                    &env,
+                    fund.into_val(&env),
+                    recipient.into_val(&env),
+                    proceeds.into_val(&env),
+                ],
+            );
+        }
+
+        events::publish(
+            &env,
+            "unstaked",
+            &operator,
+            events::Unstaked {
+                amount_xlm,
+                token,
+                proceeds,
+                recipient,
+            },
+        );
+        Ok(proceeds)
+    }
+
+    /// Books the interest earned by the subordinated tranche on Blend as yield.
+    ///
+    /// The fund's b-tokens are marked at the pool's current b-rate, and any
+    /// XLM above what the fund supplied is interest. It accrues to every
+    /// holder through the share price.
+    ///
+    /// The interest is valued at the price the tranche was bought at, not
+    /// at a router quote, on purpose: the NAV carries the whole tranche at
+    /// cost, and anyone can call this, so a quote would let a caller who
+    /// moves the pool price first choose what gets booked. The difference
+    /// to the market price is realized when the XLM is unstaked.
+    pub fn harvest_subordinated(env: Env) -> Result<i128, Error> {
+        let mut state = load_state(&env)?;
+        let addrs = load_addresses(&env)?;
+
+        let position = blend::collateral_value(&env, &addrs.blend_comet, &addrs.xlm_token);
+
+        let interest_xlm = position - state.subordinated_xlm;
+        if interest_xlm <= 0 || state.subordinated_xlm == 0 {
+            return Ok(0);
+        }
+        let interest = interest_xlm * state.total_subordinated / state.subordinated_xlm;
+
+        state.subordinated_xlm += interest_xlm;
+        state.total_subordinated += interest;
+        state.total_yield += interest;
+        save_state(&env, &state);
+
+        events::publish(
+            &env,
+            "harvested",
+            &env.current_contract_address(),
+            events::Harvested {
+                interest_xlm,
+                interest_usdc: interest,
+            },
+        );
+        Ok(interest)
+    }
+
+    /// Lends `amount_usdc` of idle senior cash to `farmer` for a loan the
+    /// payment contract accepted. The cash leaves for the farmer and is
+    /// carried as `loans_receivable` until it is repaid or written off.
+    pub fn draw_capital(env: Env, payment: Address, farmer: Address, amount_usdc: i128) -> Result<(), Error> {
+        require_payment_contract(&env, &payment)?;
+
+        if amount_usdc <= 0 {
+            return Err(Error::InvalidAmount);
+        }
+
+        let mut state = load_state(&env)?;
+        if idle_senior(&env, &state) < amount_usdc {
+            return Err(Error::InsufficientSeniorBalance);
+        }
+        state.total_senior -= amount_usdc;
+        state.loans_receivable += amount_usdc;
+        save_state(&env, &state);
+
+        let addrs = load_addresses(&env)?;
+
+        env.invoke_contract::<()>(
+            &addrs.usdc_token,
+            &Symbol::new(&env, "transfer"),
+            vec![
+                &env,
+                env.current_contract_address().into_val(&env),
+                farmer.clone().into_val(&env),
+                amount_usdc.into_val(&env),
+            ],
+        );
+
+        events::publish(
+            &env,
+            "capital_drawn",
+            &farmer,
+            events::CapitalDrawn {
+                amount_usdc,
+                loans_receivable: state.loans_receivable,
+            },
+        );
+        Ok(())
+    }
+
+    /// Books a loan repayment the payment contract has sent to the fund:
+    /// `principal` returns lent capital to senior cash and `interest`,
+    /// which includes loan fees, is senior yield.
+    pub fn repay_capital(
+        env: Env,
+        payment: Address,
+        farmer: Address,
+        principal: i128,
+        interest: i128,
+    ) -> Result<(), Error> {
+        require_payment_contract(&env, &payment)?;
+
+        let mut state = load_state(&env)?;
+        if principal < 0 || interest < 0 || principal + interest == 0 || principal > state.loans_receivable {
+            return Err(Error::InvalidAmount);
+        }
+        state.loans_receivable -= principal;
+        state.total_senior += principal + interest;
+        state.total_yield += interest;
+        save_state(&env, &state);
+
+        events::publish(
+            &env,
+            "capital_repaid",
+            &farmer,
+            events::CapitalRepaid {
+                principal,
+                interest,
+                loans_receivable: state.loans_receivable,
+            },
+        );
+        Ok(())
+    }
+
+    /// Pays part of the `interest` on an installment of `farmer`'s loan,
+    /// started at `loan_start`, out of the donation pool (see `subsidy`).
+    /// The subsidy is senior yield like the rest of the interest; the
+    /// payment contract collects only the remainder from the farmer.
+    /// Returns the subsidy.
+    pub fn subsidize_interest(
+        env: Env,
+        payment: Address,
+        farmer: Address,
+        loan_start: u64,
+        interest: i128,
+    ) -> Result<i128, Error> {
+        require_payment_contract(&env, &payment)?;
+
+        if interest < 0 {
+            return Err(Error::InvalidAmount);
+        }
+
+        let mut state = load_state(&env)?;
+        let subsidy = subsidy::grant(&env, &farmer, loan_start, interest, state.total_fund);
+        if subsidy == 0 {
+            return Ok(0);
+        }
+        state.total_fund -= subsidy;
+        state.total_senior += subsidy;
+        state.total_yield += subsidy;
+        save_state(&env, &state);
+
+        events::publish(
+            &env,
+            "interest_subsidized",
+            &farmer,
+            events::InterestSubsidized {
+                interest,
+                subsidy,
+                pool: state.total_fund,
+            },
+        );
+        Ok(subsidy)
+    }
+
+    pub fn set_subsidy(env: Env, admin: Address, subsidy: Subsidy) -> Result<(), Error> {
+        access::require_role(&env, &admin, Role::Admin)?;
+        subsidy::validate(&subsidy)?;
+
+        subsidy::write_settings(&env, &subsidy);
+
+        events::publish(&env, "subsidy_updated", &admin, subsidy);
+        Ok(())
+    }
+
+    pub fn get_subsidy(env: Env) -> Subsidy {
+        subsidy::read_settings(&env)
+    }
+
+    /// Interest subsidy `farmer`'s loan started at `loan_start` has had.
+    pub fn get_loan_subsidy(env: Env, farmer: Address, loan_start: u64) -> i128 {
+        subsidy::loan_used(&env, &farmer, loan_start)
+    }
+
+    /// Interest subsidy paid so far in the current period.
+    pub fn get_period_subsidy(env: Env) -> i128 {
+        subsidy::period_used(&env, &subsidy::read_settings(&env))
+    }
+
+    /// Covers what the guarantee pool can of a `loss` on `farmer`'s loan,
+    /// which the payment contract is writing off (see `guarantee`). The
+    /// covered capital goes back to senior, and the payment contract
+    /// writes off only the rest. Returns the amount covered.
+    pub fn claim_guarantee(env: Env, payment: Address, farmer: Address, loss: i128) -> Result<i128, Error> {
+        require_payment_contract(&env, &payment)?;
+
+        let mut state = load_state(&env)?;
+        if loss <= 0 || loss > state.loans_receivable {
+            return Err(Error::InvalidAmount);
+        }
+
+        let Some(claim) = guarantee::claim(&env, &farmer, loss) else {
+            return Ok(0);
+        };
+        state.loans_receivable -= claim.covered;
+        state.total_senior += claim.covered;
+        save_state(&env, &state);
+
+        let covered = claim.covered;
+        events::publish(&env, "guarantee_claimed", &farmer, claim);
+        Ok(covered)
+    }
+
+    /// Sets the caps of guarantee cohort `cohort`, creating it if needed.
+    pub fn set_guarantee_cohort(
+        env: Env,
+        admin: Address,
+        cohort: Symbol,
+        loan_cap: i128,
+        cohort_cap: i128,
+    ) -> Result<(), Error> {
+        access::require_role(&env, &admin, Role::Admin)?;
+
+        guarantee::set_cohort(&env, &cohort, loan_cap, cohort_cap)?;
+
+        events::publish(
+            &env,
+            "cohort_updated",
+            &admin,
+            events::CohortUpdated {
+                cohort,
+                loan_cap,
+                cohort_cap,
+            },
+        );
+        Ok(())
+    }
+
+    /// Puts `farmer` in a guarantee cohort, or with `None` takes them out.
+    pub fn set_farmer_cohort(env: Env, admin: Address, farmer: Address, cohort: Option<Symbol>) -> Result<(), Error> {
+        access::require_role(&env, &admin, Role::Admin)?;
+
+        guarantee::set_member(&env, &farmer, &cohort)?;
+
+        events::publish(&env, "cohort_assigned", &farmer, cohort);
+        Ok(())
+    }
+
+    pub fn get_farmer_cohort(env: Env, farmer: Address) -> Option<Symbol> {
+        guarantee::read_member(&env, &farmer)
+    }
+
+    /// Coverage `cohort` has used and has left.
+    pub fn get_guarantee_coverage(env: Env, cohort: Symbol) -> Result<CohortCoverage, Error> {
+        guarantee::coverage(&env, &cohort).ok_or(Error::CohortNotFound)
+    }
+
+    /// Claims the guarantee paid on `farmer`'s loans, oldest first.
+    pub fn get_guarantee_claims(env: Env, farmer: Address) -> Vec<GuaranteeClaim> {
+        guarantee::read_claims(&env, &farmer)
+    }
+
+    pub fn get_guarantee_pool(env: Env) -> i128 {
+        guarantee::pool(&env)
+    }
+
+    /// Books the capital still lent out on a loan the payment contract
+    /// wrote off as a loss, through the waterfall (see `waterfall`).
+    pub fn write_off(env: Env, payment: Address, farmer: Address, amount_usdc: i128) -> Result<LossAllocation, Error> {
+        require_payment_contract(&env, &payment)?;
+
+        let mut state = load_state(&env)?;
+        if amount_usdc <= 0 || amount_usdc > state.loans_receivable {
+            return Err(Error::InvalidAmount);
+        }
+
+        let allocation = waterfall::absorb(&env, &mut state, &farmer, amount_usdc);
+        save_state(&env, &state);
+        Ok(allocation)
+    }
+
+    /// Books USDC recovered on a written-off loan. The payment contract
+    /// sends the USDC to the fund before calling this. Once the tranches
+    /// have their losses back, the guarantee pool is refilled with what it
+    /// paid out.
+    pub fn recover(env: Env, payment: Address, farmer: Address, amount_usdc: i128) -> Result<LossAllocation, Error> {
+        require_payment_contract(&env, &payment)?;
+
+        if amount_usdc <= 0 {
+            return Err(Error::InvalidAmount);
+        }
+
+        let losses = waterfall::outstanding(&env);
+        let owed = losses.reserve + losses.subordinated + losses.senior;
+        let refill = guarantee::refill(&env, amount_usdc - owed);
+
+        let mut state = load_state(&env)?;
+        let allocation = waterfall::recover(&env, &mut state, &farmer, amount_usdc - refill);
+        save_state(&env, &state);
+
+        if refill > 0 {
+            events::publish(
+                &env,
+                "guarantee_recovered",
+                &farmer,
+                events::GuaranteeRecovered {
+                    amount_usdc: refill,
+                    pool: guarantee::pool(&env),
+                },
+            );
+        }
+        Ok(allocation)
+    }
+
+    /// Losses from written-off loans each tranche is still waiting to recover.
+    pub fn get_losses(env: Env) -> LossAllocation {
+        waterfall::outstanding(&env)
+    }
+
+    /// Subordinated book value owed to senior for the losses the tranche
+    /// absorbed, which its XLM pays when unstaked into senior.
+    pub fn get_senior_claim(env: Env) -> i128 {
+        waterfall::senior_claim(&env)
+    }
+
+    /// Burns `shares` of the investor's position and pays out their value in USDC.
+    ///
+    /// The payout is the investor's pro-rata claim on the fund, so it carries
+    /// their share of all yield booked so far. The rest of the position is
+    /// left as it is.
+    pub fn redeem_investment(env: Env, investor: Address, shares: i128) -> Result<Redemption, Error> {
+        investor.require_auth();
+        pause::require_not_paused(&env, PausableFunction::Redeem)?;
+
+        if shares <= 0 {
+            return Err(Error::InvalidAmount);
+        }
+
+        let state = load_state(&env)?;
+        let total_payment = nav::value_of_shares(&state, shares);
+
+        redeem(&env, state, &investor, shares, total_payment)
+    }
+
+    /// Burns the shares worth `amount_usdc`, rounded up so the fund never
+    /// pays for more than it burns, and pays that amount less any early-exit
+    /// fee.
+    pub fn redeem_amount(env: Env, investor: Address, amount_usdc: i128) -> Result<Redemption, Error> {
+        investor.require_auth();
+        pause::require_not_paused(&env, PausableFunction::Redeem)?;
+
+        if amount_usdc <= 0 {
+            return Err(Error::InvalidAmount);
+        }
+
+        let state = load_state(&env)?;
+        let shares = nav::shares_for_withdrawal(&state, amount_usdc);
+
+        redeem(&env, state, &investor, shares, amount_usdc)
+    }
+
+    /// Queues `shares` for redemption once the notice period has passed.
+    ///
+    /// The shares and the principal behind them are held in escrow until the
+    /// request is filled or cancelled.
+    pub fn request_redemption(env: Env, investor: Address, shares: i128) -> Result<RedemptionRequest, Error> {
+        investor.require_auth();
+        pause::require_not_paused(&env, PausableFunction::Redeem)?;
+
+        if shares <= 0 {
+            return Err(Error::InvalidAmount);
+        }
+        let mut state = load_state(&env)?;
+
+        let balance = share::balance_of(&env, &investor);
+        if balance == 0 {
+            return Err(Error::NoPosition);
+        }
+        if balance < shares {
+            return Err(Error::InsufficientShares);
+        }
+
+        // Any early-exit fee is charged now, by burning shares worth it and
+        // moving their value into the reserve, so it needs idle senior cash
+        // to move just as a redemption would.
+        let early_exit_fee = lots::take_for_exit(&env, &state, &investor, shares, balance)?;
+        let fee_shares = nav::shares_for_withdrawal(&state, early_exit_fee);
+        if fee_shares >= shares {
+            return Err(Error::InvalidAmount);
+        }
+        if fee_shares > 0 && idle_senior(&env, &state) < early_exit_fee {
+            return Err(Error::InsufficientSeniorBalance);
+        }
+        if fee_shares > 0 {
+            share::burn(&env, &mut state, &investor, fee_shares)?;
+            state.total_senior -= early_exit_fee;
+            state.total_reserve += early_exit_fee;
+            save_state(&env, &state);
+        }
+
+        let principal = position::release_principal(&env, &investor, shares, balance);
+        share::escrow_transfer(&env, &investor, &env.current_contract_address(), shares - fee_shares)?;
+        let request = queue::push(&env, &investor, shares - fee_shares, principal);
+
+        events::publish(
+            &env,
+            "redemption_requested",
+            &investor,
+            events::RedemptionRequested {
+                request: request.clone(),
+                early_exit_fee,
+            },
+        );
+        Ok(request)
+    }
+
+    /// Takes an open request out of the queue and hands back what is left
+    /// of its shares.
+    pub fn cancel_redemption(env: Env, investor: Address, id: u64) -> Result<(), Error> {
+        investor.require_auth();
+
+        let mut request = queue::read(&env, id).ok_or(Error::RequestNotFound)?;
+        if request.investor != investor {
+            return Err(Error::Unauthorized);
+        }
+
+        share::escrow_transfer(&env, &env.current_contract_address(), &investor, request.shares)?;
+        position::add_principal(&env, &investor, request.principal);
+        // Any lockup was settled when the request was made.
+        lots::give(
+            &env,
+            &investor,
             vec![
                 &env,
-                addrs.xlm_token.into_val(&env), 
-                amount.into_val(&env)
+                Lot {
+                    shares: request.shares,
+                    deposited_at: request.requested_at,
+                    unlocks_at: request.requested_at,
+                },
             ],
         );
+        let cancelled = request.clone();
+        queue::reduce(&env, &mut request, cancelled.shares, cancelled.principal);
+
+        events::publish(&env, "redemption_cancelled", &investor, cancelled);
+        Ok(())
+    }
+
+    /// Fills up to `limit` requests whose notice period has passed, oldest
+    /// first, from the senior cash on hand. The first request that cash
+    /// doesn't fully cover is filled in part and stops the run. Anyone can
+    /// call this, typically after repayments or Blend withdrawals.
+    ///
+    /// Returns how many requests were filled, in whole or in part.
+    pub fn process_redemptions(env: Env, limit: u32) -> Result<u32, Error> {
+        pause::require_not_paused(&env, PausableFunction::Redeem)?;
+
+        let mut state = load_state(&env)?;
+        let addrs = load_addresses(&env)?;
+        let fund = env.current_contract_address();
+
+        let mut filled = 0;
+        while filled < limit {
+            let Some(mut request) = queue::front(&env) else {
+                break;
+            };
+            if env.ledger().timestamp() < queue::ready_at(&env, &request) {
+                break;
+            }
+
+            let mut shares = request.shares;
+            if nav::value_of_shares(&state, shares) > state.total_senior {
+                shares = state.total_senior * state.total_shares / nav::nav(&state);
+            }
+            if shares <= 0 {
+                break;
+            }
+
+            let amount_usdc = nav::value_of_shares(&state, shares);
+            let principal = request.principal * shares / request.shares;
+            state.total_senior -= amount_usdc;
+            share::burn(&env, &mut state, &fund, shares)?;
+            queue::reduce(&env, &mut request, shares, principal);
+            save_state(&env, &state);
+
+            env.invoke_contract::<()>(
+                &addrs.usdc_token,
+                &Symbol::new(&env, "transfer"),
+                vec![
+                    &env,
+                    fund.into_val(&env),
+                    request.investor.into_val(&env),
+                    amount_usdc.into_val(&env),
+                ],
+            );
+
+            events::publish(
+                &env,
+                "redemption_filled",
+                &request.investor,
+                events::RedemptionFilled {
+                    id: request.id,
+                    redemption: Redemption {
+                        shares,
+                        amount_usdc,
+                        principal,
+                        yield_usdc: amount_usdc - principal,
+                        early_exit_fee: 0,
+                    },
+                },
+            );
+            filled += 1;
+
+            if request.shares > 0 {
+                break;
+            }
+        }
+        Ok(filled)
+    }
+
+    pub fn set_notice_period(env: Env, admin: Address, seconds: u64) -> Result<(), Error> {
+        access::require_role(&env, &admin, Role::Admin)?;
+
+        queue::set_notice_period(&env, seconds);
+
+        events::publish(&env, "notice_period_updated", &admin, seconds);
+        Ok(())
+    }
+
+    /// Sets the lockup for deposits made from now on. Existing lots keep
+    /// their unlock dates; an early-exit fee applies to every locked lot.
+    pub fn set_lockup(env: Env, admin: Address, lockup: Lockup) -> Result<(), Error> {
+        access::require_role(&env, &admin, Role::Admin)?;
+        lots::validate(&lockup)?;
+
+        lots::write_lockup(&env, &lockup);
+
+        events::publish(&env, "lockup_updated", &admin, lockup);
+        Ok(())
+    }
+
+    pub fn get_lockup(env: Env) -> Lockup {
+        lots::read_lockup(&env)
+    }
+
+    /// The investor's deposit lots and when each unlocks. Lots that have
+    /// already unlocked are reported merged into one.
+    pub fn get_lots(env: Env, investor: Address) -> Vec<Lot> {
+        lots::read_lots(&env, &investor)
+    }
+
+    pub fn get_notice_period(env: Env) -> u64 {
+        queue::notice_period(&env)
     }
 
-    pub fn redeem_investment(env: Env, investor: Address, stake_yield: i128) {
-        let mut state: FundState = env.storage().persistent()
-            .get(&STATE_KEY)
-            .unwrap_or_else(|| panic!("Contract not initialized"));
-    
-        let invested_amount = state.shares.get(investor.clone()).unwrap_or(0);
-        if invested_amount == 0 {
-            panic!("Investidor não possui saldo");
-        }
-    
-        let total_payment = invested_amount + stake_yield;
-    
-        if state.total_senior < total_payment {
-            panic!("Saldo insuficiente na cota sênior para pagamento");
-        }
-        state.total_senior -= total_payment; 
-    
-        state.shares.set(investor.clone(), 0);
-        env.storage().persistent().set(&STATE_KEY, &state);
-    
-        let addrs: ContractAddresses = env.storage().persistent()
-            .get(&ADDRS_KEY)
-            .unwrap_or_else(|| panic!("Addresses not set"));
-    
+    pub fn get_redemption_request(env: Env, id: u64) -> Result<RedemptionRequest, Error> {
+        queue::read(&env, id).ok_or(Error::RequestNotFound)
+    }
+
+    /// How many open requests will be filled before request `id`.
+    pub fn get_queue_position(env: Env, id: u64) -> Result<u32, Error> {
+        queue::read(&env, id).ok_or(Error::RequestNotFound)?;
+        Ok(queue::ahead_of(&env, id).0)
+    }
+
+    /// What request `id` would pay at today's share price, and how much of
+    /// that today's senior cash covers behind the requests ahead of it.
+    pub fn get_expected_fill(env: Env, id: u64) -> Result<ExpectedFill, Error> {
+        let state = load_state(&env)?;
+        let request = queue::read(&env, id).ok_or(Error::RequestNotFound)?;
+
+        let (_, shares_ahead) = queue::ahead_of(&env, id);
+        let cash_left = state.total_senior - nav::value_of_shares(&state, shares_ahead);
+        let amount_usdc = nav::value_of_shares(&state, request.shares);
+        Ok(ExpectedFill {
+            amount_usdc,
+            fillable_usdc: cash_left.clamp(0, amount_usdc),
+            ready_at: queue::ready_at(&env, &request),
+        })
+    }
+
+    /// Burns the investor's whole position for their pro-rata share of the
+    /// senior tranche's cash. Only available while the fund is paused.
+    ///
+    /// It touches neither the router nor Blend, so it works whatever broke.
+    /// The investor gives up their claim on the subordinated tranche and the
+    /// reserve, which stays with the remaining holders.
+    pub fn emergency_withdraw(env: Env, investor: Address) -> Result<i128, Error> {
+        investor.require_auth();
+        if !pause::is_paused(&env) {
+            return Err(Error::NotPaused);
+        }
+
+        let mut state = load_state(&env)?;
+
+        let shares = share::balance_of(&env, &investor);
+        if shares == 0 {
+            return Err(Error::NoPosition);
+        }
+
+        let payout = state.total_senior * shares / state.total_shares;
+        state.total_senior -= payout;
+
+        share::burn(&env, &mut state, &investor, shares)?;
+        // The lockup doesn't hold in an emergency.
+        lots::take(&env, &investor, shares, shares);
+        let redemption = position::redemption(&env, &investor, shares, shares, payout, 0);
+        save_state(&env, &state);
+
+        let addrs = load_addresses(&env)?;
+
         env.invoke_contract::<()>(
             &addrs.usdc_token,
             &Symbol::new(&env, "transfer"),
             vec![
                 &env,
                 env.current_contract_address().into_val(&env),
-                investor.into_val(&env),
-                total_payment.into_val(&env), 
+                investor.clone().into_val(&env),
+                payout.into_val(&env),
             ],
         );
+
+        events::publish(&env, "emergency_withdrawn", &investor, redemption);
+        Ok(payout)
     }
 
-    pub fn get_state(env: Env) -> FundState {
-        env.storage().persistent()
-            .get(&STATE_KEY)
-            .unwrap_or_else(|| panic!("Contract not initialized"))
+    /// Stops or resumes every pausable entrypoint at once.
+    pub fn set_paused(env: Env, pauser: Address, paused: bool) -> Result<(), Error> {
+        access::require_role(&env, &pauser, Role::Pauser)?;
+
+        let scope = PauseScope::All;
+        pause::set_paused(&env, &scope, paused);
+
+        events::publish(&env, "pause_updated", &pauser, events::PauseUpdated { scope, paused });
+        Ok(())
     }
+
+    /// Stops or resumes a single entrypoint. The global flag overrides it.
+    pub fn set_function_paused(
+        env: Env,
+        pauser: Address,
+        function: PausableFunction,
+        paused: bool,
+    ) -> Result<(), Error> {
+        access::require_role(&env, &pauser, Role::Pauser)?;
+
+        let scope = PauseScope::Function(function);
+        pause::set_paused(&env, &scope, paused);
+
+        events::publish(&env, "pause_updated", &pauser, events::PauseUpdated { scope, paused });
+        Ok(())
+    }
+
+    pub fn is_paused(env: Env) -> bool {
+        pause::is_paused(&env)
+    }
+
+    pub fn is_function_paused(env: Env, function: PausableFunction) -> bool {
+        pause::is_function_paused(&env, function)
+    }
+
+    pub fn update_addresses(env: Env, admin: Address, addresses: ContractAddresses) -> Result<(), Error> {
+        access::require_role(&env, &admin, Role::Admin)?;
+
+        save_addresses(&env, &addresses);
+
+        events::publish(&env, "addresses_updated", &admin, addresses);
+        Ok(())
+    }
+
+    pub fn get_addresses(env: Env) -> Result<ContractAddresses, Error> {
+        load_addresses(&env)
+    }
+
+    pub fn set_allocation(env: Env, admin: Address, allocation: Allocation) -> Result<(), Error> {
+        access::require_role(&env, &admin, Role::Admin)?;
+        allocation::validate(&allocation)?;
+
+        save_allocation(&env, &allocation);
+
+        events::publish(&env, "allocation_updated", &admin, allocation);
+        Ok(())
+    }
+
+    pub fn get_allocation(env: Env) -> Result<Allocation, Error> {
+        load_allocation(&env)
+    }
+
+    /// Registers the payment contract that lends out senior capital and
+    /// reports repayments and write-offs.
+    pub fn set_payment_contract(env: Env, admin: Address, payment: Address) -> Result<(), Error> {
+        access::require_role(&env, &admin, Role::Admin)?;
+
+        env.storage().persistent().set(&PAYMENT_KEY, &payment);
+        ttl::extend_persistent(&env, &PAYMENT_KEY);
+
+        events::publish(&env, "payment_contract_set", &admin, payment);
+        Ok(())
+    }
+
+    pub fn get_payment_contract(env: Env) -> Option<Address> {
+        read_payment_contract(&env)
+    }
+
+    pub fn grant_role(env: Env, admin: Address, role: Role, account: Address) -> Result<(), Error> {
+        access::require_role(&env, &admin, Role::Admin)?;
+
+        access::set_role(&env, role, &account, true)?;
+
+        events::publish(&env, "role_granted", &account, events::RoleChanged { role, admin });
+        Ok(())
+    }
+
+    pub fn revoke_role(env: Env, admin: Address, role: Role, account: Address) -> Result<(), Error> {
+        access::require_role(&env, &admin, Role::Admin)?;
+
+        access::set_role(&env, role, &account, false)?;
+
+        events::publish(&env, "role_revoked", &account, events::RoleChanged { role, admin });
+        Ok(())
+    }
+
+    pub fn has_role(env: Env, role: Role, account: Address) -> Result<bool, Error> {
+        access::has_role(&env, role, &account)
+    }
+
+    pub fn get_admin(env: Env) -> Result<Address, Error> {
+        access::read_admin(&env)
+    }
+
+    /// Starts an admin handover. The new admin has to call `accept_admin`
+    /// before it takes effect, so a typo can't lock the fund.
+    pub fn transfer_admin(env: Env, new_admin: Address) -> Result<(), Error> {
+        let admin = access::read_admin(&env)?;
+        admin.require_auth();
+
+        access::write_pending_admin(&env, Some(&new_admin));
+
+        events::publish(&env, "admin_transfer_started", &admin, new_admin);
+        Ok(())
+    }
+
+    pub fn accept_admin(env: Env) -> Result<(), Error> {
+        let pending = access::read_pending_admin(&env).ok_or(Error::NoPendingAdmin)?;
+        pending.require_auth();
+
+        let previous = access::read_admin(&env)?;
+        access::write_admin(&env, &pending);
+        access::write_pending_admin(&env, None);
+
+        events::publish(&env, "admin_transferred", &pending, previous);
+        Ok(())
+    }
+
+    pub fn get_state(env: Env) -> Result<FundState, Error> {
+        load_state(&env)
+    }
+
+    pub fn get_nav(env: Env) -> Result<i128, Error> {
+        Ok(nav::nav(&load_state(&env)?))
+    }
+
+    pub fn get_share_price(env: Env) -> Result<i128, Error> {
+        Ok(nav::share_price(&load_state(&env)?))
+    }
+
+    /// USDC the investor paid in for the shares they still hold.
+    pub fn get_principal(env: Env, investor: Address) -> Result<i128, Error> {
+        load_state(&env)?;
+        Ok(position::principal_of(&env, &investor))
+    }
+
+    /// Current USDC value of everything `investor` holds, yield included.
+    pub fn get_position_value(env: Env, investor: Address) -> Result<i128, Error> {
+        let state = load_state(&env)?;
+        Ok(nav::value_of_shares(&state, share::balance_of(&env, &investor)))
+    }
+
+    /// Replaces the contract's code. Storage is kept as is; run `migrate`
+    /// afterwards if the new code expects a newer layout.
+    pub fn upgrade(env: Env, admin: Address, new_wasm_hash: BytesN<32>) -> Result<(), Error> {
+        access::require_role(&env, &admin, Role::Admin)?;
+
+        events::publish(&env, "upgraded", &admin, new_wasm_hash.clone());
+        env.deployer().update_current_contract_wasm(new_wasm_hash);
+        Ok(())
+    }
+
+    /// Rewrites storage into the layout this code expects, up to `limit`
+    /// holders per call, and returns how many are left.
+    ///
+    /// Call it until it returns 0; the fund is unavailable until then.
+    pub fn migrate(env: Env, admin: Address, limit: u32) -> Result<u32, Error> {
+        access::require_role(&env, &admin, Role::Admin)?;
+
+        let remaining = migration::migrate(&env, limit);
+
+        events::publish(
+            &env,
+            "migrated",
+            &admin,
+            events::Migrated {
+                version: migration::read_version(&env),
+                remaining,
+            },
+        );
+        Ok(remaining)
+    }
+
+    /// Storage layout version currently in place.
+    pub fn get_version(env: Env) -> u32 {
+        migration::read_version(&env)
+    }
+
+    /// Bumps the instance and every fund-wide persistent entry.
+    pub fn bump_all(env: Env, admin: Address) -> Result<(), Error> {
+        access::require_role(&env, &admin, Role::Admin)?;
+
+        ttl::extend_instance(&env);
+        access::extend_ttl(&env);
+        ttl::extend_persistent_if_present(&env, &ADDRS_KEY);
+        ttl::extend_persistent_if_present(&env, &ALLOC_KEY);
+        ttl::extend_persistent_if_present(&env, &PAYMENT_KEY);
+        ttl::extend_persistent_if_present(&env, &lots::LotKey::Lockup);
+        Ok(())
+    }
+
+    /// Bumps the balance entries of holders who haven't touched the fund in
+    /// a while. Returns how many of them had a balance to bump.
+    pub fn extend_balance_ttl(env: Env, admin: Address, holders: Vec<Address>) -> Result<u32, Error> {
+        access::require_role(&env, &admin, Role::Admin)?;
+
+        let mut bumped = 0;
+        for holder in holders.iter() {
+            if share::extend_balance_ttl(&env, &holder) {
+                position::extend_ttl(&env, &holder);
+                lots::extend_ttl(&env, &holder);
+                bumped += 1;
+            }
+        }
+        Ok(bumped)
+    }
+}
+
+/// Burns `shares` for `amount_usdc` out of the senior tranche, less any
+/// early-exit fee, and splits the payout into principal and yield.
+fn redeem(
+    env: &Env,
+    mut state: FundState,
+    investor: &Address,
+    shares: i128,
+    amount_usdc: i128,
+) -> Result<Redemption, Error> {
+    let balance = share::balance_of(env, investor);
+    if balance == 0 {
+        return Err(Error::NoPosition);
+    }
+    if balance < shares {
+        return Err(Error::InsufficientShares);
+    }
+    if idle_senior(env, &state) < amount_usdc {
+        return Err(Error::InsufficientSeniorBalance);
+    }
+    let early_exit_fee = lots::take_for_exit(env, &state, investor, shares, balance)?;
+    let payout = amount_usdc - early_exit_fee;
+    state.total_senior -= amount_usdc;
+    state.total_reserve += early_exit_fee;
+
+    share::burn(env, &mut state, investor, shares)?;
+    let redemption = position::redemption(env, investor, shares, balance, payout, early_exit_fee);
+    save_state(env, &state);
+
+    let addrs = load_addresses(env)?;
+
+    env.invoke_contract::<()>(
+        &addrs.usdc_token,
+        &Symbol::new(env, "transfer"),
+        vec![
+            env,
+            env.current_contract_address().into_val(env),
+            investor.into_val(env),
+            payout.into_val(env),
+        ],
+    );
+
+    events::publish(env, "redeemed", investor, redemption.clone());
+    Ok(redemption)
+}
+
+fn issue_receipt(env: &Env, donor: &Address, amount_usdc: i128, beneficiary: Option<Address>) {
+    let receipt = donation::issue(env, donor, amount_usdc, beneficiary);
+    events::publish(env, "receipt_issued", donor, receipt);
 }
+
+/// Adds directed donations nobody claimed in time to the general pool.
+fn release_to_pool(env: &Env, state: &mut FundState, beneficiary: &Address, amount_usdc: i128) {
+    if amount_usdc == 0 {
+        return;
+    }
+    state.total_fund += amount_usdc;
+    save_state(env, state);
+
+    events::publish(env, "donation_released", beneficiary, events::DonationReleased { amount_usdc });
+}
+
+/// Senior cash not spoken for by queued redemptions, which were there first.
+fn idle_senior(env: &Env, state: &FundState) -> i128 {
+    state.total_senior - nav::value_of_shares(state, queue::queued_shares(env))
+}
+
+pub(crate) fn load_state(env: &Env) -> Result<FundState, Error> {
+    ttl::extend_instance(env);
+    match env.storage().instance().get(&STATE_KEY) {
+        Some(state) => Ok(state),
+        None if migration::has_legacy_state(env) => Err(Error::MigrationPending),
+        None => Err(Error::NotInitialized),
+    }
+}
+
+pub(crate) fn load_addresses(env: &Env) -> Result<ContractAddresses, Error> {
+    let addresses = env.storage().persistent()
+        .get(&ADDRS_KEY)
+        .ok_or(Error::NotInitialized)?;
+    ttl::extend_persistent(env, &ADDRS_KEY);
+    Ok(addresses)
+}
+
+fn save_addresses(env: &Env, addresses: &ContractAddresses) {
+    env.storage().persistent().set(&ADDRS_KEY, addresses);
+    ttl::extend_persistent(env, &ADDRS_KEY);
+}
+
+fn read_payment_contract(env: &Env) -> Option<Address> {
+    let payment = env.storage().persistent().get(&PAYMENT_KEY);
+    if payment.is_some() {
+        ttl::extend_persistent(env, &PAYMENT_KEY);
+    }
+    payment
+}
+
+/// Authenticates `caller` and checks that it is the registered payment contract.
+fn require_payment_contract(env: &Env, caller: &Address) -> Result<(), Error> {
+    caller.require_auth();
+    if read_payment_contract(env).as_ref() != Some(caller) {
+        return Err(Error::Unauthorized);
+    }
+    Ok(())
+}
+
+fn load_allocation(env: &Env) -> Result<Allocation, Error> {
+    let allocation = env.storage().persistent()
+        .get(&ALLOC_KEY)
+        .ok_or(Error::NotInitialized)?;
+    ttl::extend_persistent(env, &ALLOC_KEY);
+    Ok(allocation)
+}
+
+fn save_allocation(env: &Env, allocation: &Allocation) {
+    env.storage().persistent().set(&ALLOC_KEY, allocation);
+    ttl::extend_persistent(env, &ALLOC_KEY);
+}
+
+pub(crate) fn save_state(env: &Env, state: &FundState) {
+    env.storage().instance().set(&STATE_KEY, state);
+    ttl::extend_instance(env);
+}
+
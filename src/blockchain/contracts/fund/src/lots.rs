@@ -0,0 +1,194 @@
+//! Deposit lots and the fund's lockup.
+//!
+//! Every investment opens a lot that stays locked for the lockup period in
+//! force when it was made. Shares without a lot, such as ones issued
+//! before lots existed, count as unlocked and leave a position first, then
+//! the lots earliest-unlock first, so locked lots are only touched once the
+//! unlocked shares are used up. Lots that have unlocked are merged, so a position only ever
+//! holds one unlocked lot plus the ones still locked.
+
+use soroban_sdk::{contracttype, vec, Address, Env, Vec};
+
+use crate::{allocation::BPS_DENOMINATOR, nav, ttl, Error, FundState};
+
+/// How long new deposits are locked and what leaving early costs.
+/// Without a fee, locked shares can't be redeemed at all.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct Lockup {
+    pub period: u64,
+    pub early_exit_fee_bps: Option<u32>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct Lot {
+    pub shares: i128,
+    pub deposited_at: u64,
+    pub unlocks_at: u64,
+}
+
+#[derive(Clone)]
+#[contracttype]
+pub enum LotKey {
+    Lockup,
+    Lots(Address),
+}
+
+pub fn validate(lockup: &Lockup) -> Result<(), Error> {
+    match lockup.early_exit_fee_bps {
+        Some(fee) if fee > BPS_DENOMINATOR => Err(Error::InvalidLockup),
+        _ => Ok(()),
+    }
+}
+
+/// Deployments that never set a lockup have none.
+pub fn read_lockup(env: &Env) -> Lockup {
+    let lockup = env.storage().persistent().get(&LotKey::Lockup);
+    match lockup {
+        Some(lockup) => {
+            ttl::extend_persistent(env, &LotKey::Lockup);
+            lockup
+        }
+        None => Lockup {
+            period: 0,
+            early_exit_fee_bps: None,
+        },
+    }
+}
+
+pub fn write_lockup(env: &Env, lockup: &Lockup) {
+    env.storage().persistent().set(&LotKey::Lockup, lockup);
+    ttl::extend_persistent(env, &LotKey::Lockup);
+}
+
+pub fn read_lots(env: &Env, investor: &Address) -> Vec<Lot> {
+    let key = LotKey::Lots(investor.clone());
+    match env.storage().persistent().get(&key) {
+        Some(lots) => {
+            ttl::extend_persistent(env, &key);
+            lots
+        }
+        None => vec![env],
+    }
+}
+
+/// Writes `lots` with the unlocked ones merged and the rest ordered by
+/// unlock time.
+fn write_lots(env: &Env, investor: &Address, lots: Vec<Lot>) {
+    let now = env.ledger().timestamp();
+    let mut unlocked: Option<Lot> = None;
+    let mut locked: Vec<Lot> = vec![env];
+    for lot in lots.iter() {
+        if lot.shares <= 0 {
+            continue;
+        }
+        if lot.unlocks_at <= now {
+            unlocked = Some(match unlocked {
+                Some(merged) => Lot {
+                    shares: merged.shares + lot.shares,
+                    deposited_at: merged.deposited_at.min(lot.deposited_at),
+                    unlocks_at: merged.unlocks_at.max(lot.unlocks_at),
+                },
+                None => lot,
+            });
+            continue;
+        }
+        let at = locked
+            .iter()
+            .position(|other| other.unlocks_at > lot.unlocks_at)
+            .unwrap_or(locked.len() as usize);
+        locked.insert(at as u32, lot);
+    }
+    if let Some(unlocked) = unlocked {
+        locked.push_front(unlocked);
+    }
+
+    let key = LotKey::Lots(investor.clone());
+    if locked.is_empty() {
+        env.storage().persistent().remove(&key);
+    } else {
+        env.storage().persistent().set(&key, &locked);
+        ttl::extend_persistent(env, &key);
+    }
+}
+
+/// Opens a lot for a new deposit under the current lockup.
+pub fn open(env: &Env, investor: &Address, shares: i128) {
+    let now = env.ledger().timestamp();
+    let mut lots = read_lots(env, investor);
+    lots.push_back(Lot {
+        shares,
+        deposited_at: now,
+        unlocks_at: now + read_lockup(env).period,
+    });
+    write_lots(env, investor, lots);
+}
+
+/// Takes `shares` out of a position holding `balance` shares before they
+/// leave, and returns the lots they came from. Shares without a lot go
+/// first, then the lots earliest unlock first.
+pub fn take(env: &Env, investor: &Address, shares: i128, balance: i128) -> Vec<Lot> {
+    let mut lots = read_lots(env, investor);
+    let mut taken: Vec<Lot> = vec![env];
+    let lotless = balance - lots.iter().map(|lot| lot.shares).sum::<i128>();
+    let mut left = shares - lotless.clamp(0, shares);
+    while left > 0 && !lots.is_empty() {
+        let mut lot = lots.pop_front_unchecked();
+        let part = left.min(lot.shares);
+        taken.push_back(Lot {
+            shares: part,
+            ..lot.clone()
+        });
+        left -= part;
+        lot.shares -= part;
+        if lot.shares > 0 {
+            lots.push_front(lot);
+        }
+    }
+    write_lots(env, investor, lots);
+    taken
+}
+
+/// Adds lots taken from another position, keeping their unlock dates.
+pub fn give(env: &Env, investor: &Address, taken: Vec<Lot>) {
+    let mut lots = read_lots(env, investor);
+    lots.append(&taken);
+    write_lots(env, investor, lots);
+}
+
+/// Of `taken`, the shares that are still locked.
+pub fn locked_shares(env: &Env, taken: &Vec<Lot>) -> i128 {
+    let now = env.ledger().timestamp();
+    taken
+        .iter()
+        .filter(|lot| lot.unlocks_at > now)
+        .map(|lot| lot.shares)
+        .sum()
+}
+
+/// Takes `shares` out of a position holding `balance` for a redemption and
+/// returns the early-exit fee on the locked part, in USDC at the price in
+/// `state`. Fails with `Locked` if the lockup doesn't allow leaving early.
+pub fn take_for_exit(
+    env: &Env,
+    state: &FundState,
+    investor: &Address,
+    shares: i128,
+    balance: i128,
+) -> Result<i128, Error> {
+    let taken = take(env, investor, shares, balance);
+    let locked = locked_shares(env, &taken);
+    if locked == 0 {
+        return Ok(0);
+    }
+    match read_lockup(env).early_exit_fee_bps {
+        Some(fee_bps) => Ok(nav::value_of_shares(state, locked) * fee_bps as i128 / BPS_DENOMINATOR as i128),
+        None => Err(Error::Locked),
+    }
+}
+
+/// Bumps `investor`'s lots entry if it has one.
+pub fn extend_ttl(env: &Env, investor: &Address) -> bool {
+    ttl::extend_persistent_if_present(env, &LotKey::Lots(investor.clone()))
+}
@@ -0,0 +1,108 @@
+use soroban_sdk::{contracttype, symbol_short, Address, Env, Map, Symbol};
+
+use crate::{position, share, FundState, STATE_KEY};
+
+pub const VERSION_KEY: Symbol = symbol_short!("VERSION");
+
+/// Storage layout this code reads and writes.
+///
+/// 1. Every balance inside the `STATE_KEY` blob in persistent storage.
+/// 2. Aggregates in instance storage and one persistent entry per holder.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Deployments from before the version was stored are told apart by layout.
+pub fn read_version(env: &Env) -> u32 {
+    env.storage()
+        .instance()
+        .get(&VERSION_KEY)
+        .unwrap_or_else(|| if has_legacy_state(env) { 1 } else { 2 })
+}
+
+pub fn write_version(env: &Env, version: u32) {
+    env.storage().instance().set(&VERSION_KEY, &version);
+}
+
+/// Runs whatever steps stand between the stored layout and `SCHEMA_VERSION`.
+///
+/// Steps that can't finish in one transaction do up to `limit` units of
+/// work per call. Returns how much is left; 0 means the layout is current.
+pub fn migrate(env: &Env, limit: u32) -> u32 {
+    if read_version(env) < 2 {
+        let remaining = migrate_legacy_state(env, limit);
+        if remaining > 0 {
+            return remaining;
+        }
+    }
+    write_version(env, SCHEMA_VERSION);
+    0
+}
+
+/// `FundState` as the first release stored it, with every balance inside.
+#[derive(Clone)]
+#[contracttype]
+pub struct LegacyFundState {
+    pub shares: Map<Address, i128>,
+    pub total_senior: i128,
+    pub total_subordinated: i128,
+    pub total_fund: i128,
+}
+
+/// Shares moved out of the legacy blob so far, kept in instance storage
+/// while a migration runs over several calls.
+const MIGRATED_KEY: Symbol = symbol_short!("MIGRATED");
+
+pub fn has_legacy_state(env: &Env) -> bool {
+    env.storage().persistent().has(&STATE_KEY)
+}
+
+/// Moves up to `limit` balances out of the legacy blob into their own
+/// entries and returns how many are left.
+///
+/// Once the map is empty the aggregates move to instance storage and the
+/// legacy entry is removed. Large maps can be drained over several calls.
+/// The legacy layout kept no share total, so it is the sum of the balances
+/// moved, and the fields it didn't have start at zero.
+fn migrate_legacy_state(env: &Env, limit: u32) -> u32 {
+    let Some(mut legacy) = env
+        .storage()
+        .persistent()
+        .get::<_, LegacyFundState>(&STATE_KEY)
+    else {
+        return 0;
+    };
+
+    let mut migrated: i128 = env.storage().instance().get(&MIGRATED_KEY).unwrap_or(0);
+    let batch = legacy.shares.keys().slice(0..limit.min(legacy.shares.len()));
+    for holder in batch.iter() {
+        let balance = legacy.shares.get_unchecked(holder.clone());
+        share::write_balance(env, &holder, balance);
+        // Version 1 issued shares one for one with the USDC invested.
+        position::add_principal(env, &holder, balance);
+        legacy.shares.remove(holder);
+        migrated += balance;
+    }
+
+    let remaining = legacy.shares.len();
+    if remaining > 0 {
+        env.storage().persistent().set(&STATE_KEY, &legacy);
+        env.storage().instance().set(&MIGRATED_KEY, &migrated);
+        return remaining;
+    }
+
+    env.storage().persistent().remove(&STATE_KEY);
+    env.storage().instance().remove(&MIGRATED_KEY);
+    crate::save_state(
+        env,
+        &FundState {
+            total_shares: migrated,
+            total_senior: legacy.total_senior,
+            total_subordinated: legacy.total_subordinated,
+            subordinated_xlm: 0,
+            total_fund: legacy.total_fund,
+            total_reserve: 0,
+            loans_receivable: 0,
+            total_yield: 0,
+        },
+    );
+    0
+}
@@ -0,0 +1,56 @@
+use crate::FundState;
+
+/// Fixed-point scale used for share prices (7 decimals, same as the share token).
+pub const PRICE_SCALAR: i128 = 10_000_000;
+
+/// Net asset value of the fund, in USDC.
+///
+/// Senior cash, the subordinated Blend position, loans still owed by farmers
+/// and the reserve all belong to share holders. Donations in `total_fund` do not.
+/// The subordinated position is carried at cost, not at the day's XLM price.
+pub fn nav(state: &FundState) -> i128 {
+    state.total_senior + state.total_subordinated + state.loans_receivable + state.total_reserve
+}
+
+/// USDC value of one whole share, scaled by `PRICE_SCALAR`.
+pub fn share_price(state: &FundState) -> i128 {
+    if state.total_shares == 0 {
+        return PRICE_SCALAR;
+    }
+    nav(state) * PRICE_SCALAR / state.total_shares
+}
+
+/// Shares to mint for a deposit of `amount` USDC at the current price.
+///
+/// Rounds down so existing holders are never diluted by rounding. Once
+/// losses have wiped out the NAV behind existing shares there is no price
+/// to buy in at, so it mints none.
+pub fn shares_for_deposit(state: &FundState, amount: i128) -> i128 {
+    if state.total_shares == 0 {
+        return amount;
+    }
+    let nav = nav(state);
+    if nav <= 0 {
+        return 0;
+    }
+    amount * state.total_shares / nav
+}
+
+/// USDC paid out for burning `shares` at the current price. Rounds down.
+pub fn value_of_shares(state: &FundState, shares: i128) -> i128 {
+    if state.total_shares == 0 {
+        return 0;
+    }
+    shares * nav(state) / state.total_shares
+}
+
+/// Shares to burn for a withdrawal of `amount` USDC at the current price.
+///
+/// Rounds up so the remaining holders never pay for the rounding.
+pub fn shares_for_withdrawal(state: &FundState, amount: i128) -> i128 {
+    let nav = nav(state);
+    if state.total_shares == 0 || nav <= 0 {
+        return amount;
+    }
+    (amount * state.total_shares + nav - 1) / nav
+}
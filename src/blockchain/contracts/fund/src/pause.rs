@@ -0,0 +1,51 @@
+use soroban_sdk::{contracttype, Env};
+
+use crate::Error;
+
+/// Entrypoints that can be paused on their own.
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PausableFunction {
+    Invest,
+    Donate,
+    Redeem,
+}
+
+/// What a pause flag covers. Flags are stored under their scope in
+/// instance storage, since every pausable call reads them; an unset flag
+/// means not paused.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum PauseScope {
+    All,
+    Function(PausableFunction),
+}
+
+pub fn is_paused(env: &Env) -> bool {
+    env.storage().instance().get(&PauseScope::All).unwrap_or(false)
+}
+
+/// Whether `function` is stopped, either on its own or by the global flag.
+pub fn is_function_paused(env: &Env, function: PausableFunction) -> bool {
+    is_paused(env)
+        || env
+            .storage()
+            .instance()
+            .get(&PauseScope::Function(function))
+            .unwrap_or(false)
+}
+
+pub fn set_paused(env: &Env, scope: &PauseScope, paused: bool) {
+    if paused {
+        env.storage().instance().set(scope, &true);
+    } else {
+        env.storage().instance().remove(scope);
+    }
+}
+
+pub fn require_not_paused(env: &Env, function: PausableFunction) -> Result<(), Error> {
+    if is_function_paused(env, function) {
+        return Err(Error::Paused);
+    }
+    Ok(())
+}
@@ -0,0 +1,97 @@
+//! What each investor paid in for the shares they hold.
+//!
+//! The principal follows the shares: redeeming or transferring part of a
+//! position takes the same fraction of its principal along, so whatever a
+//! redemption pays on top of it is yield.
+
+use soroban_sdk::{contracttype, Address, Env};
+
+use crate::ttl;
+
+#[derive(Clone)]
+#[contracttype]
+pub enum PositionKey {
+    Principal(Address),
+}
+
+/// How a redemption's payout splits into returned principal and yield.
+/// `amount_usdc` is what the investor received, after `early_exit_fee`;
+/// `yield_usdc` is negative when the position lost value.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct Redemption {
+    pub shares: i128,
+    pub amount_usdc: i128,
+    pub principal: i128,
+    pub yield_usdc: i128,
+    pub early_exit_fee: i128,
+}
+
+pub fn principal_of(env: &Env, investor: &Address) -> i128 {
+    let key = PositionKey::Principal(investor.clone());
+    match env.storage().persistent().get::<_, i128>(&key) {
+        Some(principal) => {
+            ttl::extend_persistent(env, &key);
+            principal
+        }
+        None => 0,
+    }
+}
+
+fn write_principal(env: &Env, investor: &Address, amount: i128) {
+    let key = PositionKey::Principal(investor.clone());
+    if amount == 0 {
+        env.storage().persistent().remove(&key);
+    } else {
+        env.storage().persistent().set(&key, &amount);
+        ttl::extend_persistent(env, &key);
+    }
+}
+
+pub fn add_principal(env: &Env, investor: &Address, amount: i128) {
+    write_principal(env, investor, principal_of(env, investor) + amount);
+}
+
+/// Takes the principal behind `shares` out of a position of `balance`
+/// shares and returns it. Closing a position takes all of it, so no
+/// rounding dust is left behind.
+pub fn release_principal(env: &Env, investor: &Address, shares: i128, balance: i128) -> i128 {
+    let principal = principal_of(env, investor);
+    let released = if shares >= balance {
+        principal
+    } else {
+        principal * shares / balance
+    };
+    write_principal(env, investor, principal - released);
+    released
+}
+
+/// Moves the principal behind `shares` from one holder to another.
+pub fn move_principal(env: &Env, from: &Address, to: &Address, shares: i128, balance: i128) {
+    let moved = release_principal(env, from, shares, balance);
+    add_principal(env, to, moved);
+}
+
+/// Splits a payout for `shares` against the principal they release.
+pub fn redemption(
+    env: &Env,
+    investor: &Address,
+    shares: i128,
+    balance: i128,
+    amount_usdc: i128,
+    early_exit_fee: i128,
+) -> Redemption {
+    let principal = release_principal(env, investor, shares, balance);
+    Redemption {
+        shares,
+        amount_usdc,
+        principal,
+        yield_usdc: amount_usdc - principal,
+        early_exit_fee,
+    }
+}
+
+/// Bumps `investor`'s principal entry if it has one.
+pub fn extend_ttl(env: &Env, investor: &Address) -> bool {
+    ttl::extend_persistent_if_present(env, &PositionKey::Principal(investor.clone()))
+}
@@ -0,0 +1,151 @@
+//! First-in-first-out redemption queue.
+//!
+//! A queued request holds the investor's shares in escrow under the fund's
+//! own address, together with the principal behind them. Once its notice
+//! period has passed it is filled from senior cash at the share price of
+//! the moment, oldest first. Requests are numbered in arrival order;
+//! `Head` is the oldest open one and `Tail` the next number to hand out.
+//! Requests cancelled out of order leave gaps that `Head` skips over.
+
+use soroban_sdk::{contracttype, Address, Env};
+
+use crate::ttl;
+
+pub const DEFAULT_NOTICE_PERIOD: u64 = 7 * 24 * 60 * 60;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct RedemptionRequest {
+    pub id: u64,
+    pub investor: Address,
+    /// Shares still waiting to be filled.
+    pub shares: i128,
+    /// Principal behind those shares.
+    pub principal: i128,
+    pub requested_at: u64,
+}
+
+/// What a queued request would pay if filled now. `fillable_usdc` is the
+/// part today's senior cash covers once every request ahead is paid.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct ExpectedFill {
+    pub amount_usdc: i128,
+    pub fillable_usdc: i128,
+    pub ready_at: u64,
+}
+
+/// The counters are read on every redemption, so they live in instance
+/// storage; requests are persistent entries of their own.
+#[derive(Clone)]
+#[contracttype]
+pub enum QueueKey {
+    NoticePeriod,
+    Head,
+    Tail,
+    QueuedShares,
+    Request(u64),
+}
+
+pub fn notice_period(env: &Env) -> u64 {
+    env.storage()
+        .instance()
+        .get(&QueueKey::NoticePeriod)
+        .unwrap_or(DEFAULT_NOTICE_PERIOD)
+}
+
+pub fn set_notice_period(env: &Env, seconds: u64) {
+    env.storage().instance().set(&QueueKey::NoticePeriod, &seconds);
+}
+
+/// Shares sitting in the queue, which instant redemptions must leave
+/// enough senior cash for.
+pub fn queued_shares(env: &Env) -> i128 {
+    env.storage().instance().get(&QueueKey::QueuedShares).unwrap_or(0)
+}
+
+fn add_queued_shares(env: &Env, amount: i128) {
+    env.storage()
+        .instance()
+        .set(&QueueKey::QueuedShares, &(queued_shares(env) + amount));
+}
+
+fn head(env: &Env) -> u64 {
+    env.storage().instance().get(&QueueKey::Head).unwrap_or(0)
+}
+
+fn tail(env: &Env) -> u64 {
+    env.storage().instance().get(&QueueKey::Tail).unwrap_or(0)
+}
+
+pub fn read(env: &Env, id: u64) -> Option<RedemptionRequest> {
+    let key = QueueKey::Request(id);
+    let request = env.storage().persistent().get(&key);
+    if request.is_some() {
+        ttl::extend_persistent(env, &key);
+    }
+    request
+}
+
+fn write(env: &Env, request: &RedemptionRequest) {
+    let key = QueueKey::Request(request.id);
+    env.storage().persistent().set(&key, request);
+    ttl::extend_persistent(env, &key);
+}
+
+/// Appends a request to the back of the queue.
+pub fn push(env: &Env, investor: &Address, shares: i128, principal: i128) -> RedemptionRequest {
+    let id = tail(env);
+    let request = RedemptionRequest {
+        id,
+        investor: investor.clone(),
+        shares,
+        principal,
+        requested_at: env.ledger().timestamp(),
+    };
+    write(env, &request);
+    env.storage().instance().set(&QueueKey::Tail, &(id + 1));
+    add_queued_shares(env, shares);
+    request
+}
+
+/// Takes `shares` out of a request, dropping it once nothing is left.
+pub fn reduce(env: &Env, request: &mut RedemptionRequest, shares: i128, principal: i128) {
+    request.shares -= shares;
+    request.principal -= principal;
+    add_queued_shares(env, -shares);
+    if request.shares > 0 {
+        write(env, request);
+        return;
+    }
+
+    env.storage().persistent().remove(&QueueKey::Request(request.id));
+    let mut head = head(env);
+    let tail = tail(env);
+    while head < tail && !env.storage().persistent().has(&QueueKey::Request(head)) {
+        head += 1;
+    }
+    env.storage().instance().set(&QueueKey::Head, &head);
+}
+
+/// The oldest open request, if any. `Head` never rests on a gap.
+pub fn front(env: &Env) -> Option<RedemptionRequest> {
+    read(env, head(env))
+}
+
+pub fn ready_at(env: &Env, request: &RedemptionRequest) -> u64 {
+    request.requested_at + notice_period(env)
+}
+
+/// Open requests ahead of `id` and the shares they hold.
+pub fn ahead_of(env: &Env, id: u64) -> (u32, i128) {
+    let mut count = 0;
+    let mut shares = 0;
+    for ahead in head(env)..id {
+        if let Some(request) = env.storage().persistent().get::<_, RedemptionRequest>(&QueueKey::Request(ahead)) {
+            count += 1;
+            shares += request.shares;
+        }
+    }
+    (count, shares)
+}
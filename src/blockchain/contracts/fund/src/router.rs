@@ -0,0 +1,92 @@
+use soroban_sdk::{
+    auth::{ContractContext, InvokerContractAuthEntry, SubContractInvocation},
+    contracttype, vec, Address, Env, IntoVal, Symbol, Vec,
+};
+
+use crate::Error;
+
+/// Caller-supplied limits for a swap through the Soroswap router.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct SwapParams {
+    /// Smallest amount of the output token the caller accepts.
+    pub amount_out_min: i128,
+    /// Tokens to route through; first is the input token, last the output.
+    pub path: Vec<Address>,
+    /// Ledger timestamp after which the swap must not execute.
+    pub deadline: u64,
+}
+
+/// Swaps exactly `amount_in` of `token_in` for `token_out` and returns the
+/// amount of `token_out` the fund actually received.
+pub fn swap_exact_in(
+    env: &Env,
+    router: &Address,
+    token_in: &Address,
+    token_out: &Address,
+    amount_in: i128,
+    params: &SwapParams,
+) -> Result<i128, Error> {
+    let path_len = params.path.len();
+    if path_len < 2
+        || params.path.first().as_ref() != Some(token_in)
+        || params.path.last().as_ref() != Some(token_out)
+    {
+        return Err(Error::InvalidSwapPath);
+    }
+    if params.deadline < env.ledger().timestamp() {
+        return Err(Error::DeadlineExpired);
+    }
+
+    let fund = env.current_contract_address();
+
+    // The router pulls the input straight from the fund into the first pair,
+    // one call below us, so the fund has to pre-authorize that transfer.
+    let first_pair = env.invoke_contract::<Address>(
+        router,
+        &Symbol::new(env, "router_pair_for"),
+        vec![
+            env,
+            params.path.get_unchecked(0).into_val(env),
+            params.path.get_unchecked(1).into_val(env),
+        ],
+    );
+    env.authorize_as_current_contract(vec![
+        env,
+        InvokerContractAuthEntry::Contract(SubContractInvocation {
+            context: ContractContext {
+                contract: token_in.clone(),
+                fn_name: Symbol::new(env, "transfer"),
+                args: vec![
+                    env,
+                    fund.into_val(env),
+                    first_pair.into_val(env),
+                    amount_in.into_val(env),
+                ],
+            },
+            sub_invocations: vec![env],
+        }),
+    ]);
+
+    let amounts = env
+        .try_invoke_contract::<Vec<i128>, soroban_sdk::Error>(
+            router,
+            &Symbol::new(env, "swap_exact_tokens_for_tokens"),
+            vec![
+                env,
+                amount_in.into_val(env),
+                params.amount_out_min.into_val(env),
+                params.path.into_val(env),
+                fund.into_val(env),
+                params.deadline.into_val(env),
+            ],
+        )
+        .map_err(|_| Error::SwapFailed)?
+        .map_err(|_| Error::SwapFailed)?;
+
+    let amount_out = amounts.last().ok_or(Error::SwapFailed)?;
+    if amount_out < params.amount_out_min {
+        return Err(Error::SlippageExceeded);
+    }
+    Ok(amount_out)
+}
@@ -0,0 +1,278 @@
+use soroban_sdk::{
+    contractimpl, contracttype, panic_with_error, symbol_short, token::{self, TokenInterface},
+    Address, Env, String,
+};
+
+use crate::{
+    load_state, lots, position, save_state, ttl, Error, FundContract, FundContractArgs, FundContractClient,
+    FundState,
+};
+
+// Same precision as USDC on Stellar, so one share starts out worth one USDC unit.
+const DECIMALS: u32 = 7;
+const NAME: &str = "AgroFinance Fund Share";
+const SYMBOL: &str = "AGRO";
+
+#[derive(Clone)]
+#[contracttype]
+pub struct AllowanceDataKey {
+    pub from: Address,
+    pub spender: Address,
+}
+
+#[derive(Clone)]
+#[contracttype]
+pub struct AllowanceValue {
+    pub amount: i128,
+    pub expiration_ledger: u32,
+}
+
+#[derive(Clone)]
+#[contracttype]
+pub enum DataKey {
+    Allowance(AllowanceDataKey),
+    Balance(Address),
+}
+
+fn check_nonnegative_amount(amount: i128) -> Result<(), Error> {
+    if amount < 0 {
+        return Err(Error::InvalidAmount);
+    }
+    Ok(())
+}
+
+pub fn balance_of(env: &Env, id: &Address) -> i128 {
+    let key = DataKey::Balance(id.clone());
+    match env.storage().persistent().get::<_, i128>(&key) {
+        Some(balance) => {
+            ttl::extend_persistent(env, &key);
+            balance
+        }
+        None => 0,
+    }
+}
+
+/// Each holder has their own entry, so the cost of a call doesn't grow with
+/// the number of holders. Emptied entries are dropped.
+pub fn write_balance(env: &Env, id: &Address, amount: i128) {
+    let key = DataKey::Balance(id.clone());
+    if amount == 0 {
+        env.storage().persistent().remove(&key);
+    } else {
+        env.storage().persistent().set(&key, &amount);
+        ttl::extend_persistent(env, &key);
+    }
+}
+
+/// Bumps `id`'s balance entry if it has one.
+pub fn extend_balance_ttl(env: &Env, id: &Address) -> bool {
+    ttl::extend_persistent_if_present(env, &DataKey::Balance(id.clone()))
+}
+
+fn receive_balance(env: &Env, id: &Address, amount: i128) {
+    let balance = balance_of(env, id);
+    write_balance(env, id, balance + amount);
+}
+
+fn spend_balance(env: &Env, id: &Address, amount: i128) -> Result<(), Error> {
+    let balance = balance_of(env, id);
+    if balance < amount {
+        return Err(Error::InsufficientShares);
+    }
+    write_balance(env, id, balance - amount);
+    Ok(())
+}
+
+/// Issues `amount` new fund shares to `to`.
+pub fn mint(env: &Env, state: &mut FundState, to: &Address, amount: i128) -> Result<(), Error> {
+    check_nonnegative_amount(amount)?;
+    receive_balance(env, to, amount);
+    state.total_shares += amount;
+
+    let topics = (symbol_short!("mint"), env.current_contract_address(), to.clone());
+    env.events().publish(topics, amount);
+    Ok(())
+}
+
+/// Destroys `amount` fund shares held by `from`.
+pub fn burn(env: &Env, state: &mut FundState, from: &Address, amount: i128) -> Result<(), Error> {
+    check_nonnegative_amount(amount)?;
+    spend_balance(env, from, amount)?;
+    state.total_shares -= amount;
+
+    let topics = (symbol_short!("burn"), from.clone());
+    env.events().publish(topics, amount);
+    Ok(())
+}
+
+/// Moves `amount` shares without their principal, for shares the fund
+/// holds in escrow on a holder's behalf.
+pub fn escrow_transfer(env: &Env, from: &Address, to: &Address, amount: i128) -> Result<(), Error> {
+    check_nonnegative_amount(amount)?;
+    spend_balance(env, from, amount)?;
+    receive_balance(env, to, amount);
+
+    let topics = (symbol_short!("transfer"), from.clone(), to.clone());
+    env.events().publish(topics, amount);
+    Ok(())
+}
+
+fn read_allowance(env: &Env, from: &Address, spender: &Address) -> AllowanceValue {
+    let key = DataKey::Allowance(AllowanceDataKey {
+        from: from.clone(),
+        spender: spender.clone(),
+    });
+    match env.storage().temporary().get::<_, AllowanceValue>(&key) {
+        Some(allowance) if allowance.expiration_ledger >= env.ledger().sequence() => allowance,
+        _ => AllowanceValue {
+            amount: 0,
+            expiration_ledger: 0,
+        },
+    }
+}
+
+fn write_allowance(
+    env: &Env,
+    from: &Address,
+    spender: &Address,
+    amount: i128,
+    expiration_ledger: u32,
+) -> Result<(), Error> {
+    if amount > 0 && expiration_ledger < env.ledger().sequence() {
+        return Err(Error::InvalidExpirationLedger);
+    }
+
+    let key = DataKey::Allowance(AllowanceDataKey {
+        from: from.clone(),
+        spender: spender.clone(),
+    });
+    env.storage().temporary().set(
+        &key,
+        &AllowanceValue {
+            amount,
+            expiration_ledger,
+        },
+    );
+
+    if amount > 0 {
+        let live_for = expiration_ledger - env.ledger().sequence();
+        env.storage().temporary().extend_ttl(&key, live_for, live_for);
+    }
+    Ok(())
+}
+
+fn spend_allowance(env: &Env, from: &Address, spender: &Address, amount: i128) -> Result<(), Error> {
+    let allowance = read_allowance(env, from, spender);
+    if allowance.amount < amount {
+        return Err(Error::InsufficientAllowance);
+    }
+    if amount > 0 {
+        write_allowance(
+            env,
+            from,
+            spender,
+            allowance.amount - amount,
+            allowance.expiration_ledger,
+        )?;
+    }
+    Ok(())
+}
+
+fn move_shares(env: &Env, from: &Address, to: &Address, amount: i128) -> Result<(), Error> {
+    check_nonnegative_amount(amount)?;
+    // The fund's own balance is the redemption queue's escrow, which only
+    // `escrow_transfer` moves shares in and out of.
+    if *to == env.current_contract_address() {
+        return Err(Error::InvalidRecipient);
+    }
+
+    // Fails before init like every other entrypoint.
+    load_state(env)?;
+    let balance = balance_of(env, from);
+    spend_balance(env, from, amount)?;
+    receive_balance(env, to, amount);
+    position::move_principal(env, from, to, amount, balance);
+    // The lockup travels with the shares.
+    lots::give(env, to, lots::take(env, from, amount, balance));
+
+    let topics = (symbol_short!("transfer"), from.clone(), to.clone());
+    env.events().publish(topics, amount);
+    Ok(())
+}
+
+fn destroy_shares(env: &Env, from: &Address, amount: i128) -> Result<(), Error> {
+    let mut state = load_state(env)?;
+    let balance = balance_of(env, from);
+    burn(env, &mut state, from, amount)?;
+    position::release_principal(env, from, amount, balance);
+    lots::take(env, from, amount, balance);
+    save_state(env, &state);
+    Ok(())
+}
+
+// The token interface has fixed signatures, so failures surface through
+// `panic_with_error!` and still reach clients as a contract `Error` code.
+#[contractimpl]
+impl token::TokenInterface for FundContract {
+    fn allowance(env: Env, from: Address, spender: Address) -> i128 {
+        read_allowance(&env, &from, &spender).amount
+    }
+
+    fn approve(env: Env, from: Address, spender: Address, amount: i128, expiration_ledger: u32) {
+        from.require_auth();
+
+        check_nonnegative_amount(amount)
+            .and_then(|_| write_allowance(&env, &from, &spender, amount, expiration_ledger))
+            .unwrap_or_else(|e| panic_with_error!(&env, e));
+
+        let topics = (symbol_short!("approve"), from, spender);
+        env.events().publish(topics, (amount, expiration_ledger));
+    }
+
+    fn balance(env: Env, id: Address) -> i128 {
+        load_state(&env).unwrap_or_else(|e| panic_with_error!(&env, e));
+        balance_of(&env, &id)
+    }
+
+    fn transfer(env: Env, from: Address, to: Address, amount: i128) {
+        from.require_auth();
+
+        move_shares(&env, &from, &to, amount).unwrap_or_else(|e| panic_with_error!(&env, e));
+    }
+
+    fn transfer_from(env: Env, spender: Address, from: Address, to: Address, amount: i128) {
+        spender.require_auth();
+
+        check_nonnegative_amount(amount)
+            .and_then(|_| spend_allowance(&env, &from, &spender, amount))
+            .and_then(|_| move_shares(&env, &from, &to, amount))
+            .unwrap_or_else(|e| panic_with_error!(&env, e));
+    }
+
+    fn burn(env: Env, from: Address, amount: i128) {
+        from.require_auth();
+
+        destroy_shares(&env, &from, amount).unwrap_or_else(|e| panic_with_error!(&env, e));
+    }
+
+    fn burn_from(env: Env, spender: Address, from: Address, amount: i128) {
+        spender.require_auth();
+
+        check_nonnegative_amount(amount)
+            .and_then(|_| spend_allowance(&env, &from, &spender, amount))
+            .and_then(|_| destroy_shares(&env, &from, amount))
+            .unwrap_or_else(|e| panic_with_error!(&env, e));
+    }
+
+    fn decimals(_env: Env) -> u32 {
+        DECIMALS
+    }
+
+    fn name(env: Env) -> String {
+        String::from_str(&env, NAME)
+    }
+
+    fn symbol(env: Env) -> String {
+        String::from_str(&env, SYMBOL)
+    }
+}
@@ -0,0 +1,110 @@
+//! Interest equalization paid from the donation pool.
+//!
+//! When a farmer pays an installment, the donation pool in `total_fund`
+//! covers `share_bps` of its interest, so the farmer pays less while the
+//! senior tranche still earns the full contractual rate. What one loan can
+//! get over its life is capped, and so is what all loans together can get
+//! in each period of `period` seconds. The pool never pays out more than
+//! it holds.
+
+use soroban_sdk::{contracttype, Address, Env};
+
+use crate::{allocation::BPS_DENOMINATOR, ttl, Error};
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct Subsidy {
+    pub share_bps: u32,
+    pub loan_cap: i128,
+    pub period_cap: i128,
+    pub period: u64,
+}
+
+/// Subsidy paid in one period; `period` counts periods since the epoch.
+#[derive(Clone)]
+#[contracttype]
+pub struct PeriodUsage {
+    pub period: u64,
+    pub used: i128,
+}
+
+/// The settings and the current period's usage are instance storage;
+/// what each loan has had is a persistent entry keyed by farmer and the
+/// loan's start date.
+#[derive(Clone)]
+#[contracttype]
+pub enum SubsidyKey {
+    Settings,
+    PeriodUsage,
+    Loan(Address, u64),
+}
+
+pub fn validate(subsidy: &Subsidy) -> Result<(), Error> {
+    if subsidy.share_bps > BPS_DENOMINATOR || subsidy.loan_cap < 0 || subsidy.period_cap < 0 || subsidy.period == 0 {
+        return Err(Error::InvalidSubsidy);
+    }
+    Ok(())
+}
+
+/// Deployments that never configured a subsidy don't pay one.
+pub fn read_settings(env: &Env) -> Subsidy {
+    env.storage().instance().get(&SubsidyKey::Settings).unwrap_or(Subsidy {
+        share_bps: 0,
+        loan_cap: 0,
+        period_cap: 0,
+        period: 30 * 24 * 60 * 60,
+    })
+}
+
+pub fn write_settings(env: &Env, subsidy: &Subsidy) {
+    env.storage().instance().set(&SubsidyKey::Settings, subsidy);
+}
+
+/// Subsidy paid so far in the current period.
+pub fn period_used(env: &Env, settings: &Subsidy) -> i128 {
+    let current = env.ledger().timestamp() / settings.period;
+    match env.storage().instance().get::<_, PeriodUsage>(&SubsidyKey::PeriodUsage) {
+        Some(usage) if usage.period == current => usage.used,
+        _ => 0,
+    }
+}
+
+pub fn loan_used(env: &Env, farmer: &Address, loan_start: u64) -> i128 {
+    let key = SubsidyKey::Loan(farmer.clone(), loan_start);
+    match env.storage().persistent().get(&key) {
+        Some(used) => {
+            ttl::extend_persistent(env, &key);
+            used
+        }
+        None => 0,
+    }
+}
+
+/// Works out the subsidy on `interest` for a farmer's loan out of a pool
+/// of `pool` USDC, and records it against both caps.
+pub fn grant(env: &Env, farmer: &Address, loan_start: u64, interest: i128, pool: i128) -> i128 {
+    let settings = read_settings(env);
+    let period_used = period_used(env, &settings);
+    let loan_used = loan_used(env, farmer, loan_start);
+
+    let subsidy = (interest * settings.share_bps as i128 / BPS_DENOMINATOR as i128)
+        .min(settings.loan_cap - loan_used)
+        .min(settings.period_cap - period_used)
+        .min(pool)
+        .max(0);
+    if subsidy == 0 {
+        return 0;
+    }
+
+    env.storage().instance().set(
+        &SubsidyKey::PeriodUsage,
+        &PeriodUsage {
+            period: env.ledger().timestamp() / settings.period,
+            used: period_used + subsidy,
+        },
+    );
+    let key = SubsidyKey::Loan(farmer.clone(), loan_start);
+    env.storage().persistent().set(&key, &(loan_used + subsidy));
+    ttl::extend_persistent(env, &key);
+    subsidy
+}
@@ -0,0 +1,3912 @@
+extern crate std;
+
+use soroban_sdk::{
+    contract, contractimpl, contracttype,
+    testutils::{
+        storage::{Instance as _, Persistent as _}, Address as AddressTestUtils, AuthorizedFunction, Events,
+        AuthorizedInvocation, Ledger, MockAuth, MockAuthInvoke,
+    },
+    token::{StellarAssetClient, TokenClient},
+    vec, Address, Env, IntoVal, Map, String, Symbol, TryFromVal, Val, Vec,
+};
+
+use crate::{
+    blend::{
+        Positions, Request, Reserve, ReserveConfig, ReserveData, B_RATE_SCALAR,
+        REQUEST_SUPPLY_COLLATERAL, REQUEST_WITHDRAW_COLLATERAL,
+    },
+    campaign::MAX_OPEN_CAMPAIGNS,
+    directed::{DEFAULT_CLAIM_WINDOW, MAX_ESCROWED_DONORS},
+    donation::year_of,
+    migration::{LegacyFundState, SCHEMA_VERSION, VERSION_KEY},
+    queue::DEFAULT_NOTICE_PERIOD,
+    events::{self, CampaignClosed, CapitalDrawn, CapitalRepaid, Donated, DonationMatched, DonationClaimed, DonationReleased, GuaranteeDonated, GuaranteeRecovered, InterestSubsidized, Invested, LossStep, PauseUpdated, RedemptionFilled, RoleChanged, Unstaked},
+    load_state, nav::PRICE_SCALAR, save_state, share,
+    share::DataKey,
+    ttl::{DAY_IN_LEDGERS, INSTANCE_BUMP_AMOUNT, PERSISTENT_BUMP_AMOUNT}, Allocation, Campaign, CampaignProgress, CohortCoverage, GuaranteeClaim, DonationReceipt, EscrowedDonation, DonationStatement, Error, FundContract, Role, FundContractClient, FundState,
+    PausableFunction, PauseScope, Redemption, ExpectedFill, Lockup, Lot, LossAllocation, Subsidy, Tranche,
+    ContractAddresses, SwapParams, ADDRS_KEY, STATE_KEY,
+};
+
+#[contracttype]
+#[derive(Clone)]
+enum PoolKey {
+    Reserves(Address, Address),
+}
+
+/// Stand-in for the Soroswap router: one constant-product pool per token pair
+/// with Soroswap's 0.3% fee, so swaps move the price the way real ones do.
+#[contract]
+pub struct MockRouter;
+
+#[contractimpl]
+impl MockRouter {
+    /// Registers reserves the test already minted to the router.
+    pub fn add_liquidity(env: Env, token_a: Address, token_b: Address, amount_a: i128, amount_b: i128) {
+        let (reserve_a, reserve_b) = reserves(&env, &token_a, &token_b);
+        set_reserves(&env, &token_a, &token_b, reserve_a + amount_a, reserve_b + amount_b);
+    }
+
+    /// Every pool lives in the router itself, so it is also the pair address.
+    pub fn router_pair_for(env: Env, _token_a: Address, _token_b: Address) -> Address {
+        env.current_contract_address()
+    }
+
+    pub fn router_get_amounts_out(env: Env, amount_in: i128, path: Vec<Address>) -> Vec<i128> {
+        let mut amounts = vec![&env, amount_in];
+        for i in 0..path.len() - 1 {
+            let (reserve_in, reserve_out) =
+                reserves(&env, &path.get_unchecked(i), &path.get_unchecked(i + 1));
+            let amount_in_with_fee = amounts.get_unchecked(i) * 997;
+            let amount_out =
+                amount_in_with_fee * reserve_out / (reserve_in * 1000 + amount_in_with_fee);
+            amounts.push_back(amount_out);
+        }
+        amounts
+    }
+
+    pub fn swap_exact_tokens_for_tokens(
+        env: Env,
+        amount_in: i128,
+        amount_out_min: i128,
+        path: Vec<Address>,
+        to: Address,
+        deadline: u64,
+    ) -> Vec<i128> {
+        to.require_auth();
+        if env.ledger().timestamp() > deadline {
+            panic!("expired");
+        }
+
+        let amounts = Self::router_get_amounts_out(env.clone(), amount_in, path.clone());
+        let amount_out = amounts.last_unchecked();
+        if amount_out < amount_out_min {
+            panic!("insufficient output amount");
+        }
+
+        let pool = env.current_contract_address();
+        TokenClient::new(&env, &path.get_unchecked(0)).transfer(&to, &pool, &amount_in);
+        for i in 0..path.len() - 1 {
+            let (token_in, token_out) = (path.get_unchecked(i), path.get_unchecked(i + 1));
+            let (reserve_in, reserve_out) = reserves(&env, &token_in, &token_out);
+            set_reserves(
+                &env,
+                &token_in,
+                &token_out,
+                reserve_in + amounts.get_unchecked(i),
+                reserve_out - amounts.get_unchecked(i + 1),
+            );
+        }
+        TokenClient::new(&env, &path.last_unchecked()).transfer(&pool, &to, &amount_out);
+
+        amounts
+    }
+}
+
+fn reserves(env: &Env, token_in: &Address, token_out: &Address) -> (i128, i128) {
+    env.storage()
+        .instance()
+        .get(&PoolKey::Reserves(token_in.clone(), token_out.clone()))
+        .unwrap_or((0, 0))
+}
+
+fn set_reserves(env: &Env, token_a: &Address, token_b: &Address, reserve_a: i128, reserve_b: i128) {
+    let storage = env.storage().instance();
+    storage.set(&PoolKey::Reserves(token_a.clone(), token_b.clone()), &(reserve_a, reserve_b));
+    storage.set(&PoolKey::Reserves(token_b.clone(), token_a.clone()), &(reserve_b, reserve_a));
+}
+
+#[contracttype]
+#[derive(Clone)]
+enum BlendKey {
+    Reserve(Address),
+    Collateral(Address, u32),
+    ReserveCount,
+}
+
+/// Stand-in for a Blend pool: tracks collateral in b-tokens and lets the test
+/// raise the b-rate to simulate accrued interest.
+#[contract]
+pub struct MockBlendPool;
+
+#[contractimpl]
+impl MockBlendPool {
+    /// Lists `asset` with a b-rate of 1.
+    pub fn add_reserve(env: Env, asset: Address) {
+        let index: u32 = env.storage().instance().get(&BlendKey::ReserveCount).unwrap_or(0);
+        let reserve = Reserve {
+            asset: asset.clone(),
+            config: ReserveConfig {
+                index,
+                decimals: 7,
+                c_factor: 9_000_000,
+                l_factor: 9_000_000,
+                util: 5_000_000,
+                max_util: 9_500_000,
+                r_base: 100_000,
+                r_one: 500_000,
+                r_two: 5_000_000,
+                r_three: 15_000_000,
+                reactivity: 20,
+                supply_cap: i128::MAX,
+                enabled: true,
+            },
+            data: ReserveData {
+                d_rate: B_RATE_SCALAR,
+                b_rate: B_RATE_SCALAR,
+                ir_mod: 10_000_000,
+                b_supply: 0,
+                d_supply: 0,
+                backstop_credit: 0,
+                last_time: env.ledger().timestamp(),
+            },
+            scalar: 10_000_000,
+        };
+        env.storage().instance().set(&BlendKey::Reserve(asset), &reserve);
+        env.storage().instance().set(&BlendKey::ReserveCount, &(index + 1));
+    }
+
+    /// Test helper standing in for interest accrued by the pool.
+    pub fn accrue(env: Env, asset: Address, rate_bps: i128) {
+        let mut reserve = Self::get_reserve(env.clone(), asset.clone());
+        reserve.data.b_rate += reserve.data.b_rate * rate_bps / 10_000;
+        env.storage().instance().set(&BlendKey::Reserve(asset), &reserve);
+    }
+
+    pub fn get_reserve(env: Env, asset: Address) -> Reserve {
+        env.storage()
+            .instance()
+            .get(&BlendKey::Reserve(asset))
+            .expect("reserve not listed")
+    }
+
+    pub fn get_positions(env: Env, address: Address) -> Positions {
+        let count: u32 = env.storage().instance().get(&BlendKey::ReserveCount).unwrap_or(0);
+        let mut collateral = Map::new(&env);
+        for index in 0..count {
+            let b_tokens = b_tokens_of(&env, &address, index);
+            if b_tokens > 0 {
+                collateral.set(index, b_tokens);
+            }
+        }
+        Positions {
+            liabilities: Map::new(&env),
+            collateral,
+            supply: Map::new(&env),
+        }
+    }
+
+    pub fn submit(env: Env, from: Address, spender: Address, to: Address, requests: Vec<Request>) -> Positions {
+        from.require_auth();
+        if spender != from {
+            spender.require_auth();
+        }
+
+        let pool = env.current_contract_address();
+        for request in requests.iter() {
+            let mut reserve = Self::get_reserve(env.clone(), request.address.clone());
+            let index = reserve.config.index;
+            let b_rate = reserve.data.b_rate;
+            let held = b_tokens_of(&env, &from, index);
+            let token = TokenClient::new(&env, &request.address);
+
+            match request.request_type {
+                REQUEST_SUPPLY_COLLATERAL => {
+                    let minted = request.amount * B_RATE_SCALAR / b_rate;
+                    token.transfer(&spender, &pool, &request.amount);
+                    set_b_tokens(&env, &from, index, held + minted);
+                    reserve.data.b_supply += minted;
+                }
+                REQUEST_WITHDRAW_COLLATERAL => {
+                    // Rounded up so the pool never pays out more than the b-tokens are worth.
+                    let burned = (request.amount * B_RATE_SCALAR + b_rate - 1) / b_rate;
+                    if burned > held {
+                        panic!("insufficient collateral");
+                    }
+                    set_b_tokens(&env, &from, index, held - burned);
+                    reserve.data.b_supply -= burned;
+                    token.transfer(&pool, &to, &request.amount);
+                }
+                _ => panic!("unsupported request"),
+            }
+            env.storage().instance().set(&BlendKey::Reserve(request.address.clone()), &reserve);
+        }
+
+        Self::get_positions(env, from)
+    }
+}
+
+fn b_tokens_of(env: &Env, address: &Address, index: u32) -> i128 {
+    env.storage()
+        .instance()
+        .get(&BlendKey::Collateral(address.clone(), index))
+        .unwrap_or(0)
+}
+
+fn set_b_tokens(env: &Env, address: &Address, index: u32, amount: i128) {
+    env.storage()
+        .instance()
+        .set(&BlendKey::Collateral(address.clone(), index), &amount);
+}
+
+fn test_allocation() -> Allocation {
+    Allocation {
+        senior_bps: 8200,
+        subordinated_bps: 1500,
+        reserve_bps: 300,
+        donation_bps: 0,
+    }
+}
+
+/// An initialized fund on the mock router and Blend pool. Returns the env
+/// and the fund, router, pool, USDC and XLM addresses.
+fn setup() -> (Env, Address, Address, Address, Address, Address) {
+    let env = Env::default();
+    env.mock_all_auths(); 
+
+    let router_id = env.register(MockRouter, ());
+    let comet_id = env.register(MockBlendPool, ());
+    let usdc_id = env.register_stellar_asset_contract_v2(Address::generate(&env)).address();
+    let xlm_id = env.register_stellar_asset_contract_v2(Address::generate(&env)).address();
+    let fund_id = env.register(FundContract, ());
+
+    // Deep USDC/XLM pool at 10 XLM per USDC.
+    add_pool(&env, &router_id, &usdc_id, &xlm_id, POOL_USDC, 10 * POOL_USDC);
+
+    // Blend lists XLM and holds other lenders' XLM to pay interest out of.
+    MockBlendPoolClient::new(&env, &comet_id).add_reserve(&xlm_id);
+    StellarAssetClient::new(&env, &xlm_id).mint(&comet_id, &(10 * POOL_USDC));
+
+    let admin = Address::generate(&env);
+    let client = FundContractClient::new(&env, &fund_id);
+    client.init(&admin, &router_id, &comet_id, &usdc_id, &xlm_id, &test_allocation());
+
+    (env, fund_id, router_id, comet_id, usdc_id, xlm_id)
+}
+
+const POOL_USDC: i128 = 1_000_000_000;
+
+fn add_pool(env: &Env, router_id: &Address, token_a: &Address, token_b: &Address, amount_a: i128, amount_b: i128) {
+    StellarAssetClient::new(env, token_a).mint(router_id, &amount_a);
+    StellarAssetClient::new(env, token_b).mint(router_id, &amount_b);
+    MockRouterClient::new(env, router_id).add_liquidity(token_a, token_b, &amount_a, &amount_b);
+}
+
+/// USDC -> XLM swap with no minimum output and a five minute deadline.
+fn swap_params(client: &FundContractClient) -> SwapParams {
+    let addrs = client.get_addresses();
+    SwapParams {
+        amount_out_min: 0,
+        path: vec![&client.env, addrs.usdc_token, addrs.xlm_token],
+        deadline: client.env.ledger().timestamp() + 300,
+    }
+}
+
+/// XLM -> USDC swap for unstaking, with no minimum output.
+fn unstake_swap(client: &FundContractClient) -> SwapParams {
+    let addrs = client.get_addresses();
+    SwapParams {
+        amount_out_min: 0,
+        path: vec![&client.env, addrs.xlm_token, addrs.usdc_token],
+        deadline: client.env.ledger().timestamp() + 300,
+    }
+}
+
+fn mint_usdc(client: &FundContractClient, to: &Address, amount: i128) {
+    let usdc = client.get_addresses().usdc_token;
+    StellarAssetClient::new(&client.env, &usdc).mint(to, &amount);
+}
+
+/// Books `amount` of loan interest the way the payment contract does: the
+/// USDC lands in the fund and is reported through `repay_capital`.
+fn book_interest(client: &FundContractClient, amount: i128) {
+    let payment = client.get_payment_contract().unwrap_or_else(|| {
+        let payment = Address::generate(&client.env);
+        client.set_payment_contract(&client.get_admin(), &payment);
+        payment
+    });
+    mint_usdc(client, &client.address, amount);
+    client.repay_capital(&payment, &Address::generate(&client.env), &0, &amount);
+}
+
+/// Funds `investor` with the USDC it is about to put in, then invests it.
+fn invest(client: &FundContractClient, investor: &Address, amount: i128) {
+    mint_usdc(client, investor, amount);
+    client.invest(investor, &amount, &swap_params(client));
+}
+
+fn set_senior_balance(env: &Env, fund_id: &Address, new_balance: i128) {
+    env.as_contract(fund_id, || {
+        let mut state: FundState = env.storage()
+            .instance()
+            .get(&STATE_KEY)
+            .unwrap_or_else(|| panic!("Contract not initialized"));
+        
+        state.total_senior = new_balance;
+
+        env.storage()
+            .instance()
+            .set(&STATE_KEY, &state);
+    });
+}
+
+/// The most recent contract event, in the shape `Events::all` returns.
+fn last_event(env: &Env) -> Vec<(Address, Vec<Val>, Val)> {
+    let events = env.events().all();
+    events.slice(events.len() - 1..)
+}
+
+/// A fund event as `Events::all` reports it.
+fn fund_event<D: IntoVal<Env, Val>>(
+    env: &Env,
+    fund_id: &Address,
+    action: &str,
+    subject: &Address,
+    payload: D,
+) -> (Address, Vec<Val>, Val) {
+    let payload: Val = payload.into_val(env);
+    (
+        fund_id.clone(),
+        (events::CONTRACT, Symbol::new(env, action), subject.clone()).into_val(env),
+        (events::version(action), payload).into_val(env),
+    )
+}
+
+#[test]
+fn test_init_fund() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let state: FundState = client.get_state();
+
+    assert_eq!(state.total_senior, 0);
+    assert_eq!(state.total_subordinated, 0);
+    assert_eq!(state.total_fund, 0);
+    assert_eq!(state.total_shares, 0);
+}
+
+#[test]
+fn test_invest_fund() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let investor = Address::generate(&env);
+    let amount_usdc = 1000_i128;
+
+    invest(&client, &investor, amount_usdc);
+
+    let state: FundState = client.get_state();
+
+    let invested = client.balance(&investor);
+    assert_eq!(invested, amount_usdc);
+
+    assert_eq!(state.total_senior, 820);
+    assert_eq!(state.total_subordinated, 150);
+    assert_eq!(state.total_reserve, 30);
+}
+
+#[test]
+fn test_donate() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let donor = Address::generate(&env);
+    let amount_usdc = 1000;
+
+    mint_usdc(&client, &donor, amount_usdc);
+    client.donate(&donor, &amount_usdc, &None);
+
+    let state = client.get_state();
+
+    assert_eq!(state.total_fund, amount_usdc);
+    assert_eq!(client.balance(&donor), 0);
+}
+
+#[test]
+fn test_multiple_investments() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let investor1 = Address::generate(&env);
+    let investor2 = Address::generate(&env);
+    let amount1 = 1000_i128;
+    let amount2 = 500_i128;
+
+    invest(&client, &investor1, amount1);
+    invest(&client, &investor2, amount2);
+
+    let state: FundState = client.get_state();
+
+    assert_eq!(client.balance(&investor1), amount1);
+    assert_eq!(client.balance(&investor2), amount2);
+
+    let total_invested = amount1 + amount2;
+    assert_eq!(state.total_senior, total_invested * 82 / 100);
+    assert_eq!(state.total_subordinated, total_invested * 15 / 100);
+    assert_eq!(state.total_reserve, total_invested * 3 / 100);
+}
+
+#[test]
+fn test_invest_same_investor_multiple_times() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let investor = Address::generate(&env);
+    let amount1 = 1000_i128;
+    let amount2 = 500_i128;
+
+    invest(&client, &investor, amount1);
+    invest(&client, &investor, amount2);
+
+    let state: FundState = client.get_state();
+
+    let total_investor = amount1 + amount2;
+    assert_eq!(client.balance(&investor), total_investor);
+
+    assert_eq!(state.total_senior, total_investor * 82 / 100);
+    assert_eq!(state.total_subordinated, total_investor * 15 / 100);
+    assert_eq!(state.total_reserve, total_investor * 3 / 100);
+}
+
+#[test]
+fn test_unstake_subordinated_sufficient_balance() {
+    let (env, fund_id, _, _, _, xlm_id) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let xlm = TokenClient::new(&env, &xlm_id);
+
+    let investor = Address::generate(&env);
+    invest(&client, &investor, 1000_i128);
+    let supplied = client.get_state().subordinated_xlm;
+
+    let recipient = Address::generate(&env);
+    let unstake_amount = supplied / 3;
+
+    let delivered = client.unstake_subordinated(&client.get_admin(), &unstake_amount, &Some(recipient.clone()), &None);
+
+    assert_eq!(delivered, unstake_amount);
+    assert_eq!(xlm.balance(&recipient), unstake_amount);
+
+    let state = client.get_state();
+    assert_eq!(state.subordinated_xlm, supplied - unstake_amount);
+    assert_eq!(state.total_subordinated, 150 - 150 * unstake_amount / supplied);
+}
+
+#[test]
+fn test_unstake_subordinated_insufficient_balance() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let recipient = Address::generate(&env);
+    let unstake_amount = 1000_i128;
+
+    let result = client.try_unstake_subordinated(&client.get_admin(), &unstake_amount, &Some(recipient), &None);
+    assert_eq!(result, Err(Ok(Error::InsufficientSubordinatedBalance)));
+}
+
+#[test]
+fn test_redeem_empty_investment() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let investor = Address::generate(&env);
+    
+    let result = client.try_redeem_investment(&investor, &0_i128);
+    assert_eq!(result, Err(Ok(Error::InvalidAmount)));
+
+    let result = client.try_redeem_investment(&investor, &100_i128);
+    assert_eq!(result, Err(Ok(Error::NoPosition)));
+}
+
+#[test]
+fn test_redeem_investment_success() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let investor = Address::generate(&env);
+    let other_investor = Address::generate(&env);
+    let investment_amount = 1000_i128;
+
+    invest(&client, &investor, investment_amount);
+    invest(&client, &other_investor, investment_amount);
+
+    let subordinated_part = investment_amount * 15 / 100;
+    let yield_amount = subordinated_part * 10 / 100; 
+
+    book_interest(&client, yield_amount);
+    
+    let state_updated = client.get_state();
+
+    // Half of the shares, so half of the NAV including the booked yield.
+    let total_payment = investment_amount + yield_amount / 2;
+
+    client.redeem_investment(&investor, &investment_amount);
+
+    let state_after = client.get_state();
+    
+    assert_eq!(client.balance(&investor), 0);
+    assert_eq!(state_after.total_senior, state_updated.total_senior - total_payment);
+}
+
+#[test]
+fn test_redeem_insufficient_senior_balance() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let investor = Address::generate(&env);
+    let investment_amount = 1000_i128;
+
+    invest(&client, &investor, investment_amount);
+
+    set_senior_balance(&env, &fund_id, investment_amount - 1);
+
+    let result = client.try_redeem_investment(&investor, &investment_amount);
+    assert_eq!(result, Err(Ok(Error::InsufficientSeniorBalance)));
+}
+
+#[test]
+fn test_investment_calculations_100() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let investor = Address::generate(&env);
+    let amount = 100_i128;
+    
+    invest(&client, &investor, amount);
+    let state = client.get_state();
+
+    assert_eq!(state.total_senior, 82);
+    assert_eq!(state.total_subordinated, 15);
+    assert_eq!(state.total_reserve, 3);
+    assert_eq!(state.total_senior + state.total_subordinated + state.total_reserve, amount);
+}
+
+#[test]
+fn test_investment_calculations_1000() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let investor = Address::generate(&env);
+    let amount = 1000_i128;
+    
+    invest(&client, &investor, amount);
+    let state = client.get_state();
+
+    assert_eq!(state.total_senior, 820);
+    assert_eq!(state.total_subordinated, 150);
+    assert_eq!(state.total_reserve, 30);
+    assert_eq!(state.total_senior + state.total_subordinated + state.total_reserve, amount);
+}
+
+#[test]
+fn test_investment_calculations_5500() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let investor = Address::generate(&env);
+    let amount = 5500_i128;
+    
+    invest(&client, &investor, amount);
+    let state = client.get_state();
+
+    assert_eq!(state.total_senior, 4510);
+    assert_eq!(state.total_subordinated, 825);
+    assert_eq!(state.total_reserve, 165);
+    assert_eq!(state.total_senior + state.total_subordinated + state.total_reserve, amount);
+}
+
+#[test]
+fn test_investment_calculations_10000() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let investor = Address::generate(&env);
+    let amount = 10000_i128;
+    
+    invest(&client, &investor, amount);
+    let state = client.get_state();
+
+    assert_eq!(state.total_senior, 8200);
+    assert_eq!(state.total_subordinated, 1500);
+    assert_eq!(state.total_reserve, 300);
+    assert_eq!(state.total_senior + state.total_subordinated + state.total_reserve, amount);
+}
+#[test]
+fn test_share_token_metadata() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    assert_eq!(client.decimals(), 7);
+    assert_eq!(client.name(), String::from_str(&env, "AgroFinance Fund Share"));
+    assert_eq!(client.symbol(), String::from_str(&env, "AGRO"));
+}
+
+#[test]
+fn test_invest_mints_shares() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let investor = Address::generate(&env);
+    invest(&client, &investor, 1000_i128);
+    invest(&client, &investor, 500_i128);
+
+    assert_eq!(client.balance(&investor), 1500);
+    assert_eq!(client.get_state().total_shares, 1500);
+}
+
+#[test]
+fn test_transfer_shares() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let investor = Address::generate(&env);
+    let receiver = Address::generate(&env);
+    invest(&client, &investor, 1000_i128);
+
+    client.transfer(&investor, &receiver, &400_i128);
+
+    assert_eq!(client.balance(&investor), 600);
+    assert_eq!(client.balance(&receiver), 400);
+    assert_eq!(client.get_state().total_shares, 1000);
+}
+
+#[test]
+fn test_transfer_more_than_balance() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let investor = Address::generate(&env);
+    let receiver = Address::generate(&env);
+    invest(&client, &investor, 1000_i128);
+
+    let result = client.try_transfer(&investor, &receiver, &1001_i128);
+    assert_eq!(result, Err(Ok(Error::InsufficientShares.into())));
+}
+
+#[test]
+fn test_shares_cannot_be_sent_to_the_fund() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let investor = Address::generate(&env);
+    let spender = Address::generate(&env);
+    invest(&client, &investor, 1000_i128);
+    client.request_redemption(&investor, &300);
+
+    assert_eq!(client.try_transfer(&investor, &fund_id, &100), Err(Ok(Error::InvalidRecipient.into())));
+    client.approve(&investor, &spender, &100_i128, &200_u32);
+    assert_eq!(
+        client.try_transfer_from(&spender, &investor, &fund_id, &100),
+        Err(Ok(Error::InvalidRecipient.into()))
+    );
+    assert_eq!(client.balance(&fund_id), 300);
+    assert_eq!(client.balance(&investor), 700);
+}
+
+#[test]
+fn test_approve_and_transfer_from() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let investor = Address::generate(&env);
+    let spender = Address::generate(&env);
+    let receiver = Address::generate(&env);
+    invest(&client, &investor, 1000_i128);
+
+    client.approve(&investor, &spender, &300_i128, &200_u32);
+    assert_eq!(client.allowance(&investor, &spender), 300);
+
+    client.transfer_from(&spender, &investor, &receiver, &200_i128);
+
+    assert_eq!(client.allowance(&investor, &spender), 100);
+    assert_eq!(client.balance(&investor), 800);
+    assert_eq!(client.balance(&receiver), 200);
+}
+
+#[test]
+fn test_transfer_from_exceeds_allowance() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let investor = Address::generate(&env);
+    let spender = Address::generate(&env);
+    invest(&client, &investor, 1000_i128);
+
+    client.approve(&investor, &spender, &100_i128, &200_u32);
+    let result = client.try_transfer_from(&spender, &investor, &spender, &101_i128);
+    assert_eq!(result, Err(Ok(Error::InsufficientAllowance.into())));
+}
+
+#[test]
+fn test_burn_shares() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let investor = Address::generate(&env);
+    invest(&client, &investor, 1000_i128);
+
+    client.burn(&investor, &250_i128);
+
+    assert_eq!(client.balance(&investor), 750);
+    assert_eq!(client.get_state().total_shares, 750);
+}
+
+#[test]
+fn test_redeem_burns_shares() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let investor = Address::generate(&env);
+    let other_investor = Address::generate(&env);
+    invest(&client, &investor, 1000_i128);
+    invest(&client, &other_investor, 1000_i128);
+
+    client.redeem_investment(&investor, &1000_i128);
+
+    assert_eq!(client.balance(&investor), 0);
+    assert_eq!(client.get_state().total_shares, 1000);
+}
+
+#[test]
+fn test_nav_and_share_price() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    assert_eq!(client.get_nav(), 0);
+    assert_eq!(client.get_share_price(), PRICE_SCALAR);
+
+    let investor = Address::generate(&env);
+    invest(&client, &investor, 1000_i128);
+
+    assert_eq!(client.get_nav(), 1000);
+    assert_eq!(client.get_share_price(), PRICE_SCALAR);
+
+    // Senior cash grows by 1000 while the share supply stays the same.
+    set_senior_balance(&env, &fund_id, 1820);
+
+    assert_eq!(client.get_nav(), 2000);
+    assert_eq!(client.get_share_price(), 2 * PRICE_SCALAR);
+}
+
+#[test]
+fn test_no_shares_are_sold_against_a_wiped_out_nav() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let early = Address::generate(&env);
+    invest(&client, &early, 1000_i128);
+    env.as_contract(&fund_id, || {
+        let mut state = load_state(&env).unwrap();
+        state.total_senior = 0;
+        state.total_subordinated = 0;
+        state.total_reserve = 0;
+        save_state(&env, &state);
+    });
+    assert_eq!(client.get_nav(), 0);
+
+    let late = Address::generate(&env);
+    mint_usdc(&client, &late, 1000);
+    assert_eq!(
+        client.try_invest(&late, &1000, &swap_params(&client)),
+        Err(Ok(Error::InvestmentTooSmall))
+    );
+    assert_eq!(client.get_state().total_shares, 1000);
+}
+
+#[test]
+fn test_late_investor_buys_at_nav() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let early = Address::generate(&env);
+    let late = Address::generate(&env);
+    invest(&client, &early, 1000_i128);
+    set_senior_balance(&env, &fund_id, 1820);
+
+    invest(&client, &late, 1000_i128);
+
+    assert_eq!(client.balance(&early), 1000);
+    assert_eq!(client.balance(&late), 500);
+    assert_eq!(client.get_nav(), 3000);
+    assert_eq!(client.get_share_price(), 2 * PRICE_SCALAR);
+}
+
+#[test]
+fn test_redeem_at_share_price() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let early = Address::generate(&env);
+    let late = Address::generate(&env);
+    invest(&client, &early, 1000_i128);
+    set_senior_balance(&env, &fund_id, 1820);
+    invest(&client, &late, 1000_i128);
+
+    let senior_before = client.get_state().total_senior;
+
+    client.redeem_investment(&late, &500_i128);
+
+    let state = client.get_state();
+    assert_eq!(state.total_senior, senior_before - 1000);
+    assert_eq!(state.total_shares, 1000);
+    assert_eq!(client.get_share_price(), 2 * PRICE_SCALAR);
+}
+
+#[test]
+fn test_loan_interest_is_shared_pro_rata() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let big = Address::generate(&env);
+    let small = Address::generate(&env);
+    invest(&client, &big, 3000_i128);
+    invest(&client, &small, 1000_i128);
+
+    book_interest(&client, 400);
+
+    let state = client.get_state();
+    assert_eq!(state.total_yield, 400);
+    assert_eq!(client.get_position_value(&big), 3300);
+    assert_eq!(client.get_position_value(&small), 1100);
+}
+
+#[test]
+fn test_harvest_subordinated_interest() {
+    let (env, fund_id, router_id, comet_id, usdc_id, xlm_id) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let pool = MockBlendPoolClient::new(&env, &comet_id);
+
+    let investor = Address::generate(&env);
+    invest(&client, &investor, 1000_i128);
+
+    assert_eq!(client.harvest_subordinated(), 0);
+
+    // A 20% rise in the b-rate pays interest in XLM; it is booked at the
+    // position's cost basis, whatever XLM trades at by then.
+    let xlm_before = client.get_state().subordinated_xlm;
+    let accrued = xlm_before / 5;
+    let interest = accrued * 150 / xlm_before;
+    pool.accrue(&xlm_id, &2_000);
+    add_pool(&env, &router_id, &usdc_id, &xlm_id, POOL_USDC, 0);
+
+    assert_eq!(client.harvest_subordinated(), interest);
+
+    let state = client.get_state();
+    assert_eq!(state.subordinated_xlm, xlm_before + accrued);
+    assert_eq!(state.total_subordinated, 150 + interest);
+    assert_eq!(state.total_yield, interest);
+    assert_eq!(client.get_position_value(&investor), 1000 + interest);
+
+    // Nothing new to book on a second harvest.
+    assert_eq!(client.harvest_subordinated(), 0);
+}
+
+#[test]
+fn test_partial_redeem_keeps_remaining_shares() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let investor = Address::generate(&env);
+    let other_investor = Address::generate(&env);
+    invest(&client, &investor, 1000_i128);
+    invest(&client, &other_investor, 1000_i128);
+
+    client.redeem_investment(&investor, &400_i128);
+
+    assert_eq!(client.balance(&investor), 600);
+    assert_eq!(client.get_state().total_senior, 1640 - 400);
+}
+
+#[test]
+fn test_redeem_more_shares_than_owned() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let investor = Address::generate(&env);
+    let other_investor = Address::generate(&env);
+    invest(&client, &investor, 1000_i128);
+    invest(&client, &other_investor, 1000_i128);
+
+    let result = client.try_redeem_investment(&investor, &1001_i128);
+    assert_eq!(result, Err(Ok(Error::InsufficientShares)));
+}
+
+#[test]
+fn test_redeem_requires_investor_auth() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let investor = Address::generate(&env);
+    let other_investor = Address::generate(&env);
+    invest(&client, &investor, 1000_i128);
+    invest(&client, &other_investor, 1000_i128);
+
+    client.redeem_investment(&investor, &1000_i128);
+
+    assert_eq!(env.auths()[0].0, investor);
+}
+
+#[test]
+fn test_init_only_once() {
+    let (env, fund_id, router_id, comet_id, usdc_id, xlm_id) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let attacker = Address::generate(&env);
+    let result = client.try_init(&attacker, &router_id, &comet_id, &usdc_id, &xlm_id, &test_allocation());
+    assert_eq!(result, Err(Ok(Error::AlreadyInitialized)));
+}
+
+#[test]
+fn test_grant_and_revoke_role() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let admin = client.get_admin();
+    let operator = Address::generate(&env);
+
+    assert!(client.has_role(&Role::Admin, &admin));
+    assert!(client.has_role(&Role::Operator, &admin));
+    assert!(!client.has_role(&Role::Operator, &operator));
+
+    client.grant_role(&admin, &Role::Operator, &operator);
+    assert!(client.has_role(&Role::Operator, &operator));
+    assert!(!client.has_role(&Role::Pauser, &operator));
+
+    client.revoke_role(&admin, &Role::Operator, &operator);
+    assert!(!client.has_role(&Role::Operator, &operator));
+}
+
+#[test]
+fn test_grant_role_requires_admin() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let outsider = Address::generate(&env);
+    let result = client.try_grant_role(&outsider, &Role::Operator, &outsider);
+    assert_eq!(result, Err(Ok(Error::Unauthorized)));
+}
+
+#[test]
+fn test_operator_can_unstake() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let operator = Address::generate(&env);
+    client.grant_role(&client.get_admin(), &Role::Operator, &operator);
+
+    let investor = Address::generate(&env);
+    invest(&client, &investor, 1000_i128);
+
+    client.unstake_subordinated(&operator, &100_i128, &Some(operator.clone()), &None);
+}
+
+#[test]
+fn test_unstake_requires_operator() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let investor = Address::generate(&env);
+    invest(&client, &investor, 1000_i128);
+
+    let result = client.try_unstake_subordinated(&investor, &100_i128, &Some(investor.clone()), &None);
+    assert_eq!(result, Err(Ok(Error::Unauthorized)));
+}
+
+#[test]
+fn test_update_addresses_requires_admin() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let operator = Address::generate(&env);
+    client.grant_role(&client.get_admin(), &Role::Operator, &operator);
+
+    let addresses = client.get_addresses();
+    let result = client.try_update_addresses(&operator, &addresses);
+    assert_eq!(result, Err(Ok(Error::Unauthorized)));
+}
+
+#[test]
+fn test_update_addresses() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let mut addresses = client.get_addresses();
+    let new_router = Address::generate(&env);
+    addresses.soroswap_router = new_router.clone();
+
+    client.update_addresses(&client.get_admin(), &addresses);
+
+    assert_eq!(client.get_addresses().soroswap_router, new_router);
+}
+
+#[test]
+fn test_admin_handover() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let old_admin = client.get_admin();
+    let new_admin = Address::generate(&env);
+
+    client.transfer_admin(&new_admin);
+    assert_eq!(client.get_admin(), old_admin);
+
+    client.accept_admin();
+    assert_eq!(client.get_admin(), new_admin);
+    assert!(!client.has_role(&Role::Admin, &old_admin));
+}
+
+#[test]
+fn test_accept_admin_without_transfer() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let result = client.try_accept_admin();
+    assert_eq!(result, Err(Ok(Error::NoPendingAdmin)));
+}
+
+#[test]
+fn test_grant_admin_role_is_rejected() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let account = Address::generate(&env);
+    let result = client.try_grant_role(&client.get_admin(), &Role::Admin, &account);
+    assert_eq!(result, Err(Ok(Error::InvalidRole)));
+}
+
+#[test]
+fn test_calls_before_init_fail_with_not_initialized() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let fund_id = env.register(FundContract, ());
+    let client = FundContractClient::new(&env, &fund_id);
+    let investor = Address::generate(&env);
+    let swap = SwapParams {
+        amount_out_min: 0,
+        path: vec![&env, Address::generate(&env), Address::generate(&env)],
+        deadline: 0,
+    };
+
+    assert_eq!(client.try_get_state().err(), Some(Ok(Error::NotInitialized)));
+    assert_eq!(client.try_invest(&investor, &100_i128, &swap), Err(Ok(Error::NotInitialized)));
+    assert_eq!(client.try_balance(&investor), Err(Ok(Error::NotInitialized.into())));
+}
+
+#[test]
+fn test_allocation_rounding_goes_to_senior() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let investor = Address::generate(&env);
+    let amount = 99_i128;
+
+    invest(&client, &investor, amount);
+    let state = client.get_state();
+
+    // 15% of 99 and 3% of 99 round down; senior picks up the remainder.
+    assert_eq!(state.total_subordinated, 14);
+    assert_eq!(state.total_reserve, 2);
+    assert_eq!(state.total_senior, 83);
+    assert_eq!(state.total_senior + state.total_subordinated + state.total_reserve, amount);
+}
+
+#[test]
+fn test_set_allocation() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let allocation = Allocation {
+        senior_bps: 7200,
+        subordinated_bps: 0,
+        reserve_bps: 1800,
+        donation_bps: 1000,
+    };
+    client.set_allocation(&client.get_admin(), &allocation);
+    assert_eq!(client.get_allocation(), allocation);
+
+    let investor = Address::generate(&env);
+    invest(&client, &investor, 1000_i128);
+
+    let state = client.get_state();
+    assert_eq!(state.total_senior, 720);
+    assert_eq!(state.total_subordinated, 0);
+    assert_eq!(state.total_reserve, 180);
+    assert_eq!(state.total_fund, 100);
+
+    // The donated part buys no shares and is not part of the NAV.
+    assert_eq!(client.balance(&investor), 900);
+    assert_eq!(client.get_nav(), 900);
+}
+
+#[test]
+fn test_set_allocation_must_sum_to_10000_bps() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let allocation = Allocation {
+        senior_bps: 8000,
+        subordinated_bps: 1500,
+        reserve_bps: 300,
+        donation_bps: 100,
+    };
+    let result = client.try_set_allocation(&client.get_admin(), &allocation);
+    assert_eq!(result, Err(Ok(Error::InvalidAllocation)));
+}
+
+#[test]
+fn test_set_allocation_requires_admin() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let outsider = Address::generate(&env);
+    let result = client.try_set_allocation(&outsider, &test_allocation());
+    assert_eq!(result, Err(Ok(Error::Unauthorized)));
+}
+
+#[test]
+fn test_init_rejects_invalid_allocation() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let fund_id = env.register(FundContract, ());
+    let client = FundContractClient::new(&env, &fund_id);
+    let dummy = Address::generate(&env);
+
+    let allocation = Allocation {
+        senior_bps: 10_000,
+        subordinated_bps: 1,
+        reserve_bps: 0,
+        donation_bps: 0,
+    };
+    let result = client.try_init(&dummy, &dummy, &dummy, &dummy, &dummy, &allocation);
+    assert_eq!(result, Err(Ok(Error::InvalidAllocation)));
+}
+
+#[test]
+fn test_invest_pulls_usdc_from_investor() {
+    let (env, fund_id, _, _, usdc_id, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let usdc = TokenClient::new(&env, &usdc_id);
+
+    let investor = Address::generate(&env);
+    mint_usdc(&client, &investor, 1500);
+
+    let swap = swap_params(&client);
+    client.invest(&investor, &1000_i128, &swap);
+
+    assert_eq!(
+        env.auths()[0],
+        (
+            investor.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    fund_id.clone(),
+                    Symbol::new(&env, "invest"),
+                    (investor.clone(), 1000_i128, swap).into_val(&env),
+                )),
+                sub_invocations: std::vec![AuthorizedInvocation {
+                    function: AuthorizedFunction::Contract((
+                        usdc_id.clone(),
+                        Symbol::new(&env, "transfer"),
+                        (investor.clone(), fund_id.clone(), 1000_i128).into_val(&env),
+                    )),
+                    sub_invocations: std::vec![],
+                }],
+            }
+        )
+    );
+    assert_eq!(usdc.balance(&investor), 500);
+    // The subordinated 15% left for the router, the rest stays in the fund.
+    assert_eq!(usdc.balance(&fund_id), 850);
+}
+
+#[test]
+fn test_invest_rejects_non_positive_amount() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let investor = Address::generate(&env);
+
+    assert_eq!(client.try_invest(&investor, &0_i128, &swap_params(&client)), Err(Ok(Error::InvalidAmount)));
+    assert_eq!(client.try_invest(&investor, &-5_i128, &swap_params(&client)), Err(Ok(Error::InvalidAmount)));
+}
+
+#[test]
+fn test_invest_without_funds_leaves_state_untouched() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let investor = Address::generate(&env);
+
+    assert!(client.try_invest(&investor, &1000_i128, &swap_params(&client)).is_err());
+
+    let state = client.get_state();
+    assert_eq!(state.total_senior, 0);
+    assert_eq!(state.total_shares, 0);
+    assert_eq!(client.balance(&investor), 0);
+}
+
+#[test]
+fn test_invest_swaps_subordinated_part_into_blend() {
+    let (env, fund_id, router_id, comet_id, usdc_id, xlm_id) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let router = MockRouterClient::new(&env, &router_id);
+    let pool = MockBlendPoolClient::new(&env, &comet_id);
+    let xlm = TokenClient::new(&env, &xlm_id);
+
+    let investor = Address::generate(&env);
+    let quote = router.router_get_amounts_out(&150_i128, &vec![&env, usdc_id.clone(), xlm_id.clone()]);
+    let expected_xlm = quote.last_unchecked();
+    assert!(expected_xlm > 0);
+
+    invest(&client, &investor, 1000_i128);
+
+    let state = client.get_state();
+    assert_eq!(state.subordinated_xlm, expected_xlm);
+    assert_eq!(state.total_subordinated, 150);
+    // At a b-rate of 1 the fund holds one b-token per XLM supplied.
+    let index = pool.get_reserve(&xlm_id).config.index;
+    assert_eq!(pool.get_positions(&fund_id).collateral.get(index), Some(expected_xlm));
+    // The XLM bought sits in Blend on the fund's behalf, never with the investor.
+    assert_eq!(xlm.balance(&fund_id), 0);
+    assert_eq!(xlm.balance(&investor), 0);
+}
+
+#[test]
+fn test_invest_rejects_output_below_minimum() {
+    let (env, fund_id, router_id, _, usdc_id, xlm_id) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let router = MockRouterClient::new(&env, &router_id);
+
+    let investor = Address::generate(&env);
+    mint_usdc(&client, &investor, 1000);
+
+    let quote = router.router_get_amounts_out(&150_i128, &vec![&env, usdc_id, xlm_id]);
+    let mut swap = swap_params(&client);
+    swap.amount_out_min = quote.last_unchecked() + 1;
+
+    assert_eq!(client.try_invest(&investor, &1000_i128, &swap), Err(Ok(Error::SwapFailed)));
+    assert_eq!(client.get_state().total_shares, 0);
+}
+
+#[test]
+fn test_invest_rejects_expired_deadline() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let investor = Address::generate(&env);
+    mint_usdc(&client, &investor, 1000);
+
+    env.ledger().set_timestamp(1_000);
+    let mut swap = swap_params(&client);
+    swap.deadline = 999;
+
+    assert_eq!(client.try_invest(&investor, &1000_i128, &swap), Err(Ok(Error::DeadlineExpired)));
+}
+
+#[test]
+fn test_invest_rejects_path_not_ending_in_xlm() {
+    let (env, fund_id, _, _, usdc_id, xlm_id) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let investor = Address::generate(&env);
+    mint_usdc(&client, &investor, 1000);
+
+    let mut swap = swap_params(&client);
+    swap.path = vec![&env, xlm_id.clone(), usdc_id.clone()];
+    assert_eq!(client.try_invest(&investor, &1000_i128, &swap), Err(Ok(Error::InvalidSwapPath)));
+
+    swap.path = vec![&env, usdc_id];
+    assert_eq!(client.try_invest(&investor, &1000_i128, &swap), Err(Ok(Error::InvalidSwapPath)));
+}
+
+#[test]
+fn test_invest_routes_through_multi_hop_path() {
+    let (env, fund_id, router_id, _, usdc_id, xlm_id) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let router = MockRouterClient::new(&env, &router_id);
+
+    // USDC -> EURC -> XLM through two separate pools.
+    let eurc_id = env.register_stellar_asset_contract_v2(Address::generate(&env)).address();
+    add_pool(&env, &router_id, &usdc_id, &eurc_id, POOL_USDC, POOL_USDC);
+    add_pool(&env, &router_id, &eurc_id, &xlm_id, POOL_USDC, 10 * POOL_USDC);
+
+    let path = vec![&env, usdc_id, eurc_id, xlm_id];
+    let quote = router.router_get_amounts_out(&150_i128, &path);
+    assert_eq!(quote.len(), 3);
+
+    let investor = Address::generate(&env);
+    mint_usdc(&client, &investor, 1000);
+    let mut swap = swap_params(&client);
+    swap.path = path;
+    client.invest(&investor, &1000_i128, &swap);
+
+    assert_eq!(client.get_state().subordinated_xlm, quote.last_unchecked());
+}
+
+#[test]
+fn test_large_swap_gets_worse_price() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let small = Address::generate(&env);
+    let whale = Address::generate(&env);
+
+    invest(&client, &small, 1_000_000);
+    let small_xlm = client.get_state().subordinated_xlm;
+
+    invest(&client, &whale, 1_000_000_000);
+    let whale_xlm = client.get_state().subordinated_xlm - small_xlm;
+
+    // Same USDC per XLM would give 1000x; price impact and the earlier
+    // purchase make the whale's fill strictly worse.
+    assert!(whale_xlm < small_xlm * 1000);
+}
+
+#[test]
+fn test_invest_needs_only_investor_auth() {
+    let (env, fund_id, _, _, usdc_id, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let investor = Address::generate(&env);
+    mint_usdc(&client, &investor, 1000);
+    let swap = swap_params(&client);
+
+    // Only the investor signs; the fund authorizes the router's pull of its
+    // own USDC itself.
+    env.mock_auths(&[MockAuth {
+        address: &investor,
+        invoke: &MockAuthInvoke {
+            contract: &fund_id,
+            fn_name: "invest",
+            args: (investor.clone(), 1000_i128, swap.clone()).into_val(&env),
+            sub_invokes: &[MockAuthInvoke {
+                contract: &usdc_id,
+                fn_name: "transfer",
+                args: (investor.clone(), fund_id.clone(), 1000_i128).into_val(&env),
+                sub_invokes: &[],
+            }],
+        },
+    }]);
+    client.invest(&investor, &1000_i128, &swap);
+
+    assert_eq!(client.balance(&investor), 1000);
+}
+
+#[test]
+fn test_unstake_withdraws_collateral_from_blend() {
+    let (env, fund_id, _, comet_id, _, xlm_id) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let pool = MockBlendPoolClient::new(&env, &comet_id);
+    let xlm = TokenClient::new(&env, &xlm_id);
+
+    let investor = Address::generate(&env);
+    invest(&client, &investor, 1000_i128);
+    let supplied = client.get_state().subordinated_xlm;
+    let index = pool.get_reserve(&xlm_id).config.index;
+
+    // After the b-rate doubles, each XLM withdrawn burns half a b-token.
+    pool.accrue(&xlm_id, &10_000);
+    client.unstake_subordinated(&client.get_admin(), &200_i128, &Some(investor.clone()), &None);
+
+    assert_eq!(xlm.balance(&investor), 200);
+    assert_eq!(pool.get_positions(&fund_id).collateral.get(index), Some(supplied - 100));
+}
+
+#[test]
+fn test_harvest_marks_b_tokens_at_current_rate() {
+    let (env, fund_id, _, comet_id, _, xlm_id) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let pool = MockBlendPoolClient::new(&env, &comet_id);
+
+    let investor = Address::generate(&env);
+    invest(&client, &investor, 1000_i128);
+    let supplied = client.get_state().subordinated_xlm;
+
+    pool.accrue(&xlm_id, &10_000);
+    assert_eq!(client.harvest_subordinated(), 150);
+    assert_eq!(client.get_state().subordinated_xlm, 2 * supplied);
+
+    // Interest keeps compounding on the b-tokens already held.
+    pool.accrue(&xlm_id, &5_000);
+    assert_eq!(client.harvest_subordinated(), 150);
+    assert_eq!(client.get_state().subordinated_xlm, 3 * supplied);
+    assert_eq!(client.get_position_value(&investor), 1300);
+}
+
+#[test]
+fn test_unstake_into_senior_swaps_back_to_usdc() {
+    let (env, fund_id, router_id, _, usdc_id, xlm_id) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let router = MockRouterClient::new(&env, &router_id);
+    let usdc = TokenClient::new(&env, &usdc_id);
+
+    let investor = Address::generate(&env);
+    invest(&client, &investor, 1000_i128);
+    let supplied = client.get_state().subordinated_xlm;
+    let quote = router.router_get_amounts_out(&supplied, &vec![&env, xlm_id, usdc_id]);
+    let usdc_before = usdc.balance(&fund_id);
+
+    let delivered = client.unstake_subordinated(&client.get_admin(), &supplied, &None, &Some(unstake_swap(&client)));
+
+    assert_eq!(delivered, quote.last_unchecked());
+    assert_eq!(usdc.balance(&fund_id), usdc_before + delivered);
+
+    let state = client.get_state();
+    assert_eq!(state.subordinated_xlm, 0);
+    assert_eq!(state.total_subordinated, 0);
+    assert_eq!(state.total_senior, 820 + delivered);
+}
+
+#[test]
+fn test_unstake_to_recipient_in_usdc() {
+    let (env, fund_id, _, _, usdc_id, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let usdc = TokenClient::new(&env, &usdc_id);
+
+    let investor = Address::generate(&env);
+    invest(&client, &investor, 1000_i128);
+    let supplied = client.get_state().subordinated_xlm;
+
+    let recipient = Address::generate(&env);
+    let delivered = client.unstake_subordinated(&client.get_admin(), &(supplied / 2), &Some(recipient.clone()), &Some(unstake_swap(&client)));
+
+    assert!(delivered > 0);
+    assert_eq!(usdc.balance(&recipient), delivered);
+    let state = client.get_state();
+    assert_eq!(state.total_senior, 820);
+    assert_eq!(state.total_subordinated, 150 - 150 * (supplied / 2) / supplied);
+}
+
+#[test]
+fn test_unstake_into_senior_requires_swap() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let investor = Address::generate(&env);
+    invest(&client, &investor, 1000_i128);
+
+    let result = client.try_unstake_subordinated(&client.get_admin(), &100_i128, &None, &None);
+    assert_eq!(result, Err(Ok(Error::SwapRequired)));
+}
+
+#[test]
+fn test_unstake_rejects_swap_below_minimum() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let investor = Address::generate(&env);
+    invest(&client, &investor, 1000_i128);
+    let supplied = client.get_state().subordinated_xlm;
+
+    let mut swap = unstake_swap(&client);
+    swap.amount_out_min = 1_000;
+
+    let result = client.try_unstake_subordinated(&client.get_admin(), &supplied, &None, &Some(swap));
+    assert_eq!(result, Err(Ok(Error::SwapFailed)));
+    assert_eq!(client.get_state().subordinated_xlm, supplied);
+}
+
+#[test]
+fn test_unstake_rejects_non_positive_amount() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let investor = Address::generate(&env);
+    invest(&client, &investor, 1000_i128);
+
+    let result = client.try_unstake_subordinated(&client.get_admin(), &0_i128, &Some(investor.clone()), &None);
+    assert_eq!(result, Err(Ok(Error::InvalidAmount)));
+}
+
+/// Puts the fund back into the version 1 layout: every balance inside one
+/// `STATE_KEY` blob in persistent storage, and no stored version. The
+/// totals are split 82/15/3 the way the first release's `invest` did.
+fn write_legacy_state(env: &Env, fund_id: &Address, holders: &[(Address, i128)]) {
+    env.as_contract(fund_id, || {
+        let mut shares = Map::new(env);
+        for (holder, balance) in holders {
+            shares.set(holder.clone(), *balance);
+        }
+        let invested: i128 = holders.iter().map(|(_, balance)| balance).sum();
+        let legacy = LegacyFundState {
+            shares,
+            total_senior: invested * 82 / 100,
+            total_subordinated: invested * 15 / 100,
+            total_fund: invested * 3 / 100,
+        };
+        env.storage().instance().remove(&STATE_KEY);
+        env.storage().instance().remove(&VERSION_KEY);
+        env.storage().persistent().set(&STATE_KEY, &legacy);
+    });
+}
+
+#[test]
+fn test_migrate_moves_legacy_balances() {
+    let (env, _, router_id, comet_id, usdc_id, xlm_id) = setup();
+    let fund_id = env.register(FundContract, ());
+    let client = FundContractClient::new(&env, &fund_id);
+
+    // A first version deployment: its addresses and the blob, no admin.
+    let holders = [
+        (Address::generate(&env), 100_i128),
+        (Address::generate(&env), 200_i128),
+        (Address::generate(&env), 300_i128),
+    ];
+    env.as_contract(&fund_id, || {
+        let addresses = ContractAddresses {
+            soroswap_router: router_id.clone(),
+            blend_comet: comet_id.clone(),
+            usdc_token: usdc_id.clone(),
+            xlm_token: xlm_id.clone(),
+        };
+        env.storage().persistent().set(&ADDRS_KEY, &addresses);
+    });
+    write_legacy_state(&env, &fund_id, &holders);
+    assert_eq!(client.try_get_admin(), Err(Ok(Error::NotInitialized)));
+    assert_eq!(client.get_version(), 1);
+
+    // Taking it over gives it an admin and leaves the balances to migrate.
+    let admin = Address::generate(&env);
+    client.init(&admin, &router_id, &comet_id, &usdc_id, &xlm_id, &test_allocation());
+    assert_eq!(client.get_version(), 1);
+    assert_eq!(client.try_get_state().err(), Some(Ok(Error::MigrationPending)));
+    assert_eq!(
+        client.try_init(&admin, &router_id, &comet_id, &usdc_id, &xlm_id, &test_allocation()),
+        Err(Ok(Error::AlreadyInitialized))
+    );
+
+    assert_eq!(client.migrate(&admin, &2), 1);
+    assert_eq!(client.try_get_nav(), Err(Ok(Error::MigrationPending)));
+    assert_eq!(client.migrate(&admin, &2), 0);
+    assert_eq!(client.get_version(), SCHEMA_VERSION);
+
+    // The share total is rebuilt from the balances; the rest starts at zero.
+    let state = client.get_state();
+    assert_eq!(state.total_shares, 600);
+    assert_eq!(state.total_senior, 492);
+    assert_eq!(state.total_subordinated, 90);
+    assert_eq!(state.total_fund, 18);
+    assert_eq!(state.subordinated_xlm, 0);
+    assert_eq!(state.total_reserve, 0);
+    assert_eq!(state.loans_receivable, 0);
+    assert_eq!(state.total_yield, 0);
+    for (holder, balance) in holders.iter() {
+        assert_eq!(client.balance(holder), *balance);
+        assert_eq!(client.get_principal(holder), *balance);
+    }
+    env.as_contract(&fund_id, || {
+        assert!(!env.storage().persistent().has(&STATE_KEY));
+    });
+
+    // Nothing left to do on a second run.
+    assert_eq!(client.migrate(&admin, &2), 0);
+}
+
+#[test]
+fn test_migrate_requires_admin() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let attacker = Address::generate(&env);
+    assert_eq!(client.try_migrate(&attacker, &10), Err(Ok(Error::Unauthorized)));
+}
+
+#[test]
+fn test_balance_entry_ttl_is_extended_on_access() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let investor = Address::generate(&env);
+    invest(&client, &investor, 1000_i128);
+
+    let key = DataKey::Balance(investor.clone());
+    let ttl = || env.as_contract(&fund_id, || env.storage().persistent().get_ttl(&key));
+    assert_eq!(ttl(), PERSISTENT_BUMP_AMOUNT);
+
+    // Once the entry is within a day of the threshold, reading it bumps it again.
+    env.ledger().with_mut(|li| li.sequence_number += 2 * DAY_IN_LEDGERS);
+    assert_eq!(ttl(), PERSISTENT_BUMP_AMOUNT - 2 * DAY_IN_LEDGERS);
+    client.balance(&investor);
+    assert_eq!(ttl(), PERSISTENT_BUMP_AMOUNT);
+}
+
+/// Release build of the first version, which kept every balance in the
+/// `STATE_KEY` blob (see `make testdata`), with the router and Blend pool
+/// as it called them.
+mod fund_v1 {
+    use soroban_sdk::{contract, contractimpl, Address, Env};
+
+    soroban_sdk::contractimport!(file = "testdata/fund_v1.wasm");
+
+    #[contract]
+    pub struct Router;
+
+    #[contractimpl]
+    impl Router {
+        pub fn swap_exact_tokens_for_tokens(_env: Env, _token_in: Address, _amount_in: i128, _token_out: Address, _to: Address) {}
+    }
+
+    #[contract]
+    pub struct Blend;
+
+    #[contractimpl]
+    impl Blend {
+        pub fn supply(_env: Env, _token: Address, _amount: i128) {}
+    }
+}
+
+/// What `f` costs in CPU instructions and memory bytes.
+fn budget_of(env: &Env, f: impl FnOnce()) -> (u64, u64) {
+    let mut budget = env.cost_estimate().budget();
+    budget.reset_unlimited();
+    f();
+    (budget.cpu_instruction_cost(), budget.memory_bytes_cost())
+}
+
+/// Compares what an `invest` and a `redeem_investment` call cost with the
+/// first version's blob against the per-investor layout as the number of
+/// holders grows.
+#[test]
+fn test_storage_layout_budget() {
+    // Every call of the first version loaded the whole blob, touched one
+    // holder and wrote the whole blob back. It runs as wasm, so its costs
+    // also include starting the VM; what they gain between 10 and 500
+    // holders is the blob alone.
+    fn legacy_calls(holders: u32) -> ((u64, u64), (u64, u64)) {
+        let env = Env::default();
+        env.mock_all_auths();
+        env.cost_estimate().budget().reset_unlimited();
+        let usdc_id = env.register_stellar_asset_contract_v2(Address::generate(&env)).address();
+        let fund_id = env.register(fund_v1::WASM, ());
+        let client = fund_v1::Client::new(&env, &fund_id);
+        let (router_id, comet_id) = (env.register(fund_v1::Router, ()), env.register(fund_v1::Blend, ()));
+        client.init(&router_id, &comet_id, &usdc_id, &Address::generate(&env));
+
+        let investors: std::vec::Vec<(Address, i128)> =
+            (0..holders).map(|_| (Address::generate(&env), 1_000)).collect();
+        write_legacy_state(&env, &fund_id, &investors);
+        StellarAssetClient::new(&env, &usdc_id).mint(&fund_id, &(holders as i128 * 1_000));
+
+        let invest = budget_of(&env, || client.invest(&Address::generate(&env), &1_000));
+        let redeem = budget_of(&env, || client.redeem_investment(&investors[0].0, &0));
+        (invest, redeem)
+    }
+
+    // A transaction only loads the entries in its footprint, so other
+    // holders' entries never enter the picture: the same calls now read the
+    // aggregates and the caller's own entries whatever the number of
+    // holders. The test host keeps every entry in one map, so the others are
+    // counted into the aggregates without being written.
+    fn current_calls(holders: u32) -> ((u64, u64), (u64, u64)) {
+        let (env, fund_id, _, _, _, _) = setup();
+        let client = FundContractClient::new(&env, &fund_id);
+
+        let holder = Address::generate(&env);
+        invest(&client, &holder, 1_000);
+        env.as_contract(&fund_id, || {
+            let mut state = load_state(&env).unwrap();
+            state.total_shares += (holders - 1) as i128 * 1_000;
+            state.total_senior += (holders - 1) as i128 * 1_000;
+            save_state(&env, &state);
+        });
+        mint_usdc(&client, &fund_id, (holders - 1) as i128 * 1_000);
+
+        let investor = Address::generate(&env);
+        mint_usdc(&client, &investor, 1_000);
+        let swap = swap_params(&client);
+        let invest = budget_of(&env, || client.invest(&investor, &1_000, &swap));
+        let redeem = budget_of(&env, || {
+            client.redeem_investment(&holder, &1_000);
+        });
+        (invest, redeem)
+    }
+
+    let (legacy_small, current_small) = (legacy_calls(10), current_calls(10));
+    let (legacy_large, current_large) = (legacy_calls(500), current_calls(500));
+
+    for (legacy_small, legacy_large, current_small, current_large) in [
+        (legacy_small.0, legacy_large.0, current_small.0, current_large.0),
+        (legacy_small.1, legacy_large.1, current_small.1, current_large.1),
+    ] {
+        // The blob makes every call grow with the holders...
+        assert!(legacy_large.0 > legacy_small.0 * 11 / 10);
+        assert!(legacy_large.1 > legacy_small.1 * 11 / 10);
+        // ...while the per-investor layout costs the same whatever their
+        // number, and less than the blob once there are many of them.
+        assert_eq!(current_large, current_small);
+        assert!(legacy_large.0 > current_large.0);
+        assert!(legacy_large.1 > current_large.1);
+    }
+}
+
+/// Moves the ledger forward, keeping the router, Blend and token instances
+/// alive as their own maintainers would on the real network.
+fn advance_days(client: &FundContractClient, days: u32) {
+    let env = &client.env;
+    let addrs = client.get_addresses();
+    let live_for = (days + 1) * DAY_IN_LEDGERS;
+    for id in [addrs.soroswap_router, addrs.blend_comet, addrs.usdc_token, addrs.xlm_token] {
+        env.as_contract(&id, || env.storage().instance().extend_ttl(live_for, live_for));
+    }
+    env.ledger().with_mut(|li| li.sequence_number += days * DAY_IN_LEDGERS);
+}
+
+#[test]
+fn test_calls_bump_instance_and_touched_entries() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let investor = Address::generate(&env);
+    invest(&client, &investor, 1000_i128);
+
+    advance_days(&client, 6);
+    let addrs_ttl = || env.as_contract(&fund_id, || env.storage().persistent().get_ttl(&ADDRS_KEY));
+    let instance_ttl = || env.as_contract(&fund_id, || env.storage().instance().get_ttl());
+    assert_eq!(instance_ttl(), INSTANCE_BUMP_AMOUNT - 6 * DAY_IN_LEDGERS);
+    assert_eq!(addrs_ttl(), PERSISTENT_BUMP_AMOUNT - 6 * DAY_IN_LEDGERS);
+
+    // A view bumps the instance only.
+    client.get_nav();
+    assert_eq!(instance_ttl(), INSTANCE_BUMP_AMOUNT);
+    assert_eq!(addrs_ttl(), PERSISTENT_BUMP_AMOUNT - 6 * DAY_IN_LEDGERS);
+
+    // Investing again reads the config, so the config entries get bumped too.
+    invest(&client, &Address::generate(&env), 1000_i128);
+    assert_eq!(addrs_ttl(), PERSISTENT_BUMP_AMOUNT);
+}
+
+#[test]
+fn test_maintenance_keeps_idle_fund_alive() {
+    let (env, fund_id, _, _, usdc_id, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let admin = client.get_admin();
+
+    let investor = Address::generate(&env);
+    let other_investor = Address::generate(&env);
+    invest(&client, &investor, 1000_i128);
+    invest(&client, &other_investor, 1000_i128);
+    let holders = vec![&env, investor.clone(), other_investor.clone()];
+
+    // Half a year with nobody but the admin touching the fund, well past
+    // every entry's original TTL.
+    for _ in 0..30 {
+        env.ledger().with_mut(|li| li.sequence_number += 6 * DAY_IN_LEDGERS);
+        client.bump_all(&admin);
+        assert_eq!(client.extend_balance_ttl(&admin, &holders), 2);
+    }
+
+    assert_eq!(client.get_state().total_shares, 2000);
+    assert_eq!(client.get_addresses().usdc_token, usdc_id);
+    assert_eq!(client.get_allocation(), test_allocation());
+    assert_eq!(client.balance(&investor), 1000);
+    assert_eq!(client.get_position_value(&other_investor), 1000);
+}
+
+#[test]
+fn test_extend_balance_ttl_skips_accounts_without_shares() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let investor = Address::generate(&env);
+    invest(&client, &investor, 1000_i128);
+
+    let holders = vec![&env, investor, Address::generate(&env)];
+    assert_eq!(client.extend_balance_ttl(&client.get_admin(), &holders), 1);
+}
+
+#[test]
+fn test_maintenance_requires_admin() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let attacker = Address::generate(&env);
+    assert_eq!(client.try_bump_all(&attacker), Err(Ok(Error::Unauthorized)));
+    assert_eq!(
+        client.try_extend_balance_ttl(&attacker, &vec![&env, attacker.clone()]),
+        Err(Ok(Error::Unauthorized))
+    );
+}
+
+/// Release build of this code, see `make testdata`.
+mod fund_wasm {
+    soroban_sdk::contractimport!(file = "testdata/fund.wasm");
+}
+
+#[test]
+fn test_upgrade_keeps_positions() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let admin = client.get_admin();
+
+    let investor = Address::generate(&env);
+    let other_investor = Address::generate(&env);
+    invest(&client, &investor, 1000);
+    invest(&client, &other_investor, 3000);
+    let state_before = client.get_state();
+
+    // Uploading a release build costs more than the default test budget.
+    env.cost_estimate().budget().reset_unlimited();
+    let new_wasm_hash = env.deployer().upload_contract_wasm(fund_wasm::WASM);
+    client.upgrade(&admin, &new_wasm_hash);
+
+    // From here on the calls run the uploaded build.
+    let upgraded = fund_wasm::Client::new(&env, &fund_id);
+    assert_eq!(upgraded.migrate(&admin, &100), 0);
+    assert_eq!(upgraded.get_version(), SCHEMA_VERSION);
+    assert_eq!(upgraded.get_admin(), admin);
+
+    let state = upgraded.get_state();
+    assert_eq!(state.total_shares, state_before.total_shares);
+    assert_eq!(state.total_senior, state_before.total_senior);
+    assert_eq!(state.total_subordinated, state_before.total_subordinated);
+    assert_eq!(state.subordinated_xlm, state_before.subordinated_xlm);
+    assert_eq!(state.total_reserve, state_before.total_reserve);
+    assert_eq!(upgraded.balance(&investor), 1000);
+    assert_eq!(upgraded.get_position_value(&other_investor), 3000);
+
+    upgraded.redeem_investment(&investor, &400);
+    assert_eq!(upgraded.balance(&investor), 600);
+}
+
+#[test]
+fn test_upgrade_requires_admin() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let attacker = Address::generate(&env);
+    env.cost_estimate().budget().reset_unlimited();
+    let new_wasm_hash = env.deployer().upload_contract_wasm(fund_wasm::WASM);
+    assert_eq!(client.try_upgrade(&attacker, &new_wasm_hash), Err(Ok(Error::Unauthorized)));
+}
+
+#[test]
+fn test_pause_blocks_investor_entrypoints() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let admin = client.get_admin();
+    let investor = Address::generate(&env);
+    invest(&client, &investor, 1000);
+
+    client.set_paused(&admin, &true);
+    assert!(client.is_paused());
+    assert!(client.is_function_paused(&PausableFunction::Invest));
+
+    mint_usdc(&client, &investor, 1000);
+    assert_eq!(client.try_invest(&investor, &1000, &swap_params(&client)), Err(Ok(Error::Paused)));
+    assert_eq!(client.try_donate(&investor, &100, &None), Err(Ok(Error::Paused)));
+    assert_eq!(client.try_redeem_investment(&investor, &100), Err(Ok(Error::Paused)));
+
+    client.set_paused(&admin, &false);
+    assert!(!client.is_paused());
+    client.invest(&investor, &1000, &swap_params(&client));
+    client.redeem_investment(&investor, &100);
+}
+
+#[test]
+fn test_function_pause_only_blocks_that_function() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let admin = client.get_admin();
+    let investor = Address::generate(&env);
+    invest(&client, &investor, 1000);
+
+    client.set_function_paused(&admin, &PausableFunction::Redeem, &true);
+    assert!(!client.is_paused());
+    assert!(client.is_function_paused(&PausableFunction::Redeem));
+    assert!(!client.is_function_paused(&PausableFunction::Invest));
+
+    assert_eq!(client.try_redeem_investment(&investor, &100), Err(Ok(Error::Paused)));
+    invest(&client, &investor, 1000);
+
+    // Lifting the global pause leaves a function-level pause in place.
+    client.set_paused(&admin, &true);
+    client.set_paused(&admin, &false);
+    assert!(client.is_function_paused(&PausableFunction::Redeem));
+
+    client.set_function_paused(&admin, &PausableFunction::Redeem, &false);
+    client.redeem_investment(&investor, &100);
+}
+
+#[test]
+fn test_pause_requires_pauser_role() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let admin = client.get_admin();
+    let pauser = Address::generate(&env);
+
+    assert_eq!(client.try_set_paused(&pauser, &true), Err(Ok(Error::Unauthorized)));
+    assert_eq!(
+        client.try_set_function_paused(&pauser, &PausableFunction::Invest, &true),
+        Err(Ok(Error::Unauthorized))
+    );
+
+    client.grant_role(&admin, &Role::Pauser, &pauser);
+    client.set_paused(&pauser, &true);
+    assert!(client.is_paused());
+    client.set_paused(&pauser, &false);
+    assert!(!client.is_paused());
+}
+
+#[test]
+fn test_pause_toggles_emit_events() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let admin = client.get_admin();
+
+    client.set_paused(&admin, &true);
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            fund_event(
+                &env,
+                &fund_id,
+                "pause_updated",
+                &admin,
+                PauseUpdated {
+                    scope: PauseScope::All,
+                    paused: true,
+                },
+            ),
+        ]
+    );
+
+    client.set_function_paused(&admin, &PausableFunction::Donate, &false);
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            fund_event(
+                &env,
+                &fund_id,
+                "pause_updated",
+                &admin,
+                PauseUpdated {
+                    scope: PauseScope::Function(PausableFunction::Donate),
+                    paused: false,
+                },
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_emergency_withdraw_pays_pro_rata_senior_cash() {
+    let (env, fund_id, _, _, usdc_id, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let admin = client.get_admin();
+    let investor = Address::generate(&env);
+    let other_investor = Address::generate(&env);
+    invest(&client, &investor, 1000);
+    invest(&client, &other_investor, 3000);
+
+    client.set_paused(&admin, &true);
+
+    let before = client.get_state();
+    let shares = client.balance(&investor);
+    let expected = before.total_senior * shares / before.total_shares;
+
+    assert_eq!(client.emergency_withdraw(&investor), expected);
+
+    let after = client.get_state();
+    assert_eq!(client.balance(&investor), 0);
+    assert_eq!(client.balance(&other_investor), 3000);
+    assert_eq!(TokenClient::new(&env, &usdc_id).balance(&investor), expected);
+    assert_eq!(after.total_senior, before.total_senior - expected);
+    assert_eq!(after.total_shares, before.total_shares - shares);
+    // The subordinated tranche is left where it is.
+    assert_eq!(after.subordinated_xlm, before.subordinated_xlm);
+
+    assert_eq!(client.try_emergency_withdraw(&investor), Err(Ok(Error::NoPosition)));
+}
+
+#[test]
+fn test_emergency_withdraw_requires_pause() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let admin = client.get_admin();
+    let investor = Address::generate(&env);
+    invest(&client, &investor, 1000);
+
+    assert_eq!(client.try_emergency_withdraw(&investor), Err(Ok(Error::NotPaused)));
+
+    // A single paused function is not an emergency.
+    client.set_function_paused(&admin, &PausableFunction::Redeem, &true);
+    assert_eq!(client.try_emergency_withdraw(&investor), Err(Ok(Error::NotPaused)));
+}
+
+#[test]
+fn test_investor_actions_emit_events() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let investor = Address::generate(&env);
+    invest(&client, &investor, 1000);
+    // `Events::all` only covers the latest call, so read it before anything else.
+    let invested = last_event(&env);
+    let subordinated_xlm = client.get_state().subordinated_xlm;
+    assert_eq!(
+        invested,
+        vec![
+            &env,
+            fund_event(
+                &env,
+                &fund_id,
+                "invested",
+                &investor,
+                Invested {
+                    amount_usdc: 1000,
+                    shares: 1000,
+                    senior: 820,
+                    subordinated: 150,
+                    subordinated_xlm,
+                    reserve: 30,
+                    donation: 0,
+                },
+            ),
+        ]
+    );
+
+    mint_usdc(&client, &investor, 50);
+    client.donate(&investor, &50, &None);
+    let events = env.events().all();
+    assert_eq!(
+        events.slice(events.len() - 2..),
+        vec![
+            &env,
+            fund_event(
+                &env,
+                &fund_id,
+                "donated",
+                &investor,
+                Donated {
+                    amount_usdc: 50,
+                    beneficiary: None,
+                },
+            ),
+            fund_event(
+                &env,
+                &fund_id,
+                "receipt_issued",
+                &investor,
+                DonationReceipt {
+                    id: 0,
+                    donor: investor.clone(),
+                    amount_usdc: 50,
+                    timestamp: 0,
+                    beneficiary: None,
+                },
+            ),
+        ]
+    );
+
+    client.redeem_investment(&investor, &400);
+    // The donation belongs to the fund, not to the shares.
+    let amount_usdc = 400;
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            fund_event(
+                &env,
+                &fund_id,
+                "redeemed",
+                &investor,
+                Redemption {
+                    shares: 400,
+                    amount_usdc,
+                    principal: 400,
+                    yield_usdc: 0,
+                    early_exit_fee: 0,
+                },
+            ),
+        ]
+    );
+}
+
+/// The payload version an event was published with.
+fn event_version(env: &Env, event: (Address, Vec<Val>, Val)) -> u32 {
+    let data = Vec::<Val>::try_from_val(env, &event.2).unwrap();
+    u32::try_from_val(env, &data.get_unchecked(0)).unwrap()
+}
+
+#[test]
+fn test_events_carry_their_own_payload_version() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let investor = Address::generate(&env);
+    invest(&client, &investor, 1000);
+    assert_eq!(event_version(&env, last_event(&env).get_unchecked(0)), 1);
+
+    mint_usdc(&client, &investor, 50);
+    client.donate(&investor, &50, &None);
+    assert_eq!(event_version(&env, last_event(&env).get_unchecked(0)), 2);
+
+    client.redeem_investment(&investor, &400);
+    assert_eq!(event_version(&env, last_event(&env).get_unchecked(0)), 3);
+}
+
+#[test]
+fn test_unstake_emits_event() {
+    let (env, fund_id, _, _, usdc_id, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let admin = client.get_admin();
+    invest(&client, &Address::generate(&env), 1000);
+    let amount_xlm = client.get_state().subordinated_xlm / 2;
+
+    let recipient = Address::generate(&env);
+    let proceeds = client.unstake_subordinated(&admin, &amount_xlm, &Some(recipient.clone()), &Some(unstake_swap(&client)));
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            fund_event(
+                &env,
+                &fund_id,
+                "unstaked",
+                &admin,
+                Unstaked {
+                    amount_xlm,
+                    token: usdc_id,
+                    proceeds,
+                    recipient: Some(recipient),
+                },
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_config_changes_emit_events() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    let admin = client.get_admin();
+    let operator = Address::generate(&env);
+
+    client.grant_role(&admin, &Role::Operator, &operator);
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            fund_event(
+                &env,
+                &fund_id,
+                "role_granted",
+                &operator,
+                RoleChanged {
+                    role: Role::Operator,
+                    admin: admin.clone(),
+                },
+            ),
+        ]
+    );
+
+    let allocation = Allocation {
+        senior_bps: 8000,
+        subordinated_bps: 1500,
+        reserve_bps: 300,
+        donation_bps: 200,
+    };
+    client.set_allocation(&admin, &allocation);
+    assert_eq!(
+        last_event(&env),
+        vec![&env, fund_event(&env, &fund_id, "allocation_updated", &admin, allocation)]
+    );
+
+    let new_admin = Address::generate(&env);
+    client.transfer_admin(&new_admin);
+    assert_eq!(
+        last_event(&env),
+        vec![&env, fund_event(&env, &fund_id, "admin_transfer_started", &admin, new_admin.clone())]
+    );
+    client.accept_admin();
+    assert_eq!(
+        last_event(&env),
+        vec![&env, fund_event(&env, &fund_id, "admin_transferred", &new_admin, admin)]
+    );
+}
+
+/// Two investors put in 1000 each and the fund books 300 of loan interest,
+/// so every share is worth 1.15 USDC.
+fn setup_with_yield(client: &FundContractClient) -> (Address, Address) {
+    let investor = Address::generate(&client.env);
+    let other_investor = Address::generate(&client.env);
+    invest(client, &investor, 1000);
+    invest(client, &other_investor, 1000);
+
+    book_interest(client, 300);
+    (investor, other_investor)
+}
+
+#[test]
+fn test_partial_redeem_by_shares_splits_principal_and_yield() {
+    let (env, fund_id, _, _, usdc_id, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let (investor, other_investor) = setup_with_yield(&client);
+
+    let redemption = client.redeem_investment(&investor, &400);
+    assert_eq!(
+        redemption,
+        Redemption {
+            shares: 400,
+            amount_usdc: 460,
+            principal: 400,
+            yield_usdc: 60,
+            early_exit_fee: 0,
+        }
+    );
+    assert_eq!(TokenClient::new(&env, &usdc_id).balance(&investor), 460);
+
+    // The rest of the position is untouched.
+    assert_eq!(client.balance(&investor), 600);
+    assert_eq!(client.get_principal(&investor), 600);
+    assert_eq!(client.get_position_value(&investor), 690);
+    assert_eq!(client.get_principal(&other_investor), 1000);
+
+    let rest = client.redeem_investment(&investor, &600);
+    assert_eq!(rest.principal, 600);
+    assert_eq!(rest.yield_usdc, 90);
+    assert_eq!(client.get_principal(&investor), 0);
+}
+
+#[test]
+fn test_redeem_amount_pays_exact_usdc() {
+    let (env, fund_id, _, _, usdc_id, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let (investor, _) = setup_with_yield(&client);
+    let senior_before = client.get_state().total_senior;
+
+    let redemption = client.redeem_amount(&investor, &230);
+    assert_eq!(
+        redemption,
+        Redemption {
+            shares: 200,
+            amount_usdc: 230,
+            principal: 200,
+            yield_usdc: 30,
+            early_exit_fee: 0,
+        }
+    );
+    assert_eq!(TokenClient::new(&env, &usdc_id).balance(&investor), 230);
+    assert_eq!(client.balance(&investor), 800);
+    assert_eq!(client.get_principal(&investor), 800);
+    assert_eq!(client.get_state().total_senior, senior_before - 230);
+}
+
+#[test]
+fn test_redeem_amount_rounds_shares_up() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let (investor, _) = setup_with_yield(&client);
+
+    // 1 USDC is 0.87 shares, so a whole share is burned for it.
+    let redemption = client.redeem_amount(&investor, &1);
+    assert_eq!(redemption.shares, 1);
+    assert_eq!(redemption.amount_usdc, 1);
+    assert_eq!(client.balance(&investor), 999);
+}
+
+#[test]
+fn test_redeem_amount_beyond_position() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let (investor, _) = setup_with_yield(&client);
+
+    assert_eq!(client.try_redeem_amount(&investor, &1151), Err(Ok(Error::InsufficientShares)));
+    assert_eq!(client.try_redeem_amount(&investor, &0), Err(Ok(Error::InvalidAmount)));
+    assert_eq!(
+        client.try_redeem_amount(&Address::generate(&env), &10),
+        Err(Ok(Error::NoPosition))
+    );
+}
+
+#[test]
+fn test_share_transfer_carries_principal() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let (investor, other_investor) = setup_with_yield(&client);
+
+    client.transfer(&investor, &other_investor, &250);
+    assert_eq!(client.get_principal(&investor), 750);
+    assert_eq!(client.get_principal(&other_investor), 1250);
+
+    let redemption = client.redeem_investment(&other_investor, &1250);
+    assert_eq!(redemption.principal, 1250);
+    assert_eq!(redemption.yield_usdc, 1250 * 3 / 20);
+}
+
+/// Moves senior cash into loans, as lending to farmers does, without
+/// changing the NAV. A negative amount is a repayment.
+fn lend_out(env: &Env, fund_id: &Address, amount: i128) {
+    env.as_contract(fund_id, || {
+        let mut state = load_state(env).unwrap();
+        state.total_senior -= amount;
+        state.loans_receivable += amount;
+        save_state(env, &state);
+    });
+}
+
+fn advance_time(env: &Env, seconds: u64) {
+    env.ledger().with_mut(|ledger| ledger.timestamp += seconds);
+}
+
+#[test]
+fn test_queued_redemption_fills_after_notice_period() {
+    let (env, fund_id, _, _, usdc_id, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let (investor, _) = setup_with_yield(&client);
+
+    let request = client.request_redemption(&investor, &400);
+    assert_eq!(request.id, 0);
+    assert_eq!(request.principal, 400);
+    assert_eq!(client.get_notice_period(), DEFAULT_NOTICE_PERIOD);
+
+    // The shares wait in escrow and keep their value until the fill.
+    assert_eq!(client.balance(&investor), 600);
+    assert_eq!(client.balance(&fund_id), 400);
+    assert_eq!(client.get_principal(&investor), 600);
+    assert_eq!(client.get_state().total_shares, 2000);
+
+    assert_eq!(client.process_redemptions(&10), 0);
+    advance_time(&env, DEFAULT_NOTICE_PERIOD);
+
+    assert_eq!(client.process_redemptions(&10), 1);
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            fund_event(
+                &env,
+                &fund_id,
+                "redemption_filled",
+                &investor,
+                RedemptionFilled {
+                    id: 0,
+                    redemption: Redemption {
+                        shares: 400,
+                        amount_usdc: 460,
+                        principal: 400,
+                        yield_usdc: 60,
+                        early_exit_fee: 0,
+                    },
+                },
+            ),
+        ]
+    );
+    assert_eq!(TokenClient::new(&env, &usdc_id).balance(&investor), 460);
+    assert_eq!(client.balance(&fund_id), 0);
+    assert_eq!(client.get_state().total_shares, 1600);
+    assert_eq!(client.try_get_redemption_request(&0), Err(Ok(Error::RequestNotFound)));
+}
+
+#[test]
+fn test_queue_fills_in_order_as_cash_frees_up() {
+    let (env, fund_id, _, _, usdc_id, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let usdc = TokenClient::new(&env, &usdc_id);
+
+    let first_investor = Address::generate(&env);
+    let second_investor = Address::generate(&env);
+    for investor in [&first_investor, &second_investor, &Address::generate(&env)] {
+        invest(&client, investor, 1000);
+    }
+
+    let first = client.request_redemption(&first_investor, &1000);
+    let second = client.request_redemption(&second_investor, &1000);
+    assert_eq!(client.get_queue_position(&first.id), 0);
+    assert_eq!(client.get_queue_position(&second.id), 1);
+
+    // Most of the senior cash is out on loans.
+    lend_out(&env, &fund_id, 1660);
+    assert_eq!(
+        client.get_expected_fill(&first.id),
+        ExpectedFill {
+            amount_usdc: 1000,
+            fillable_usdc: 800,
+            ready_at: DEFAULT_NOTICE_PERIOD,
+        }
+    );
+    assert_eq!(client.get_expected_fill(&second.id).fillable_usdc, 0);
+
+    // The first request takes all the cash there is and the second waits.
+    advance_time(&env, DEFAULT_NOTICE_PERIOD);
+    assert_eq!(client.process_redemptions(&10), 1);
+    assert_eq!(usdc.balance(&first_investor), 800);
+    assert_eq!(usdc.balance(&second_investor), 0);
+    assert_eq!(client.get_redemption_request(&first.id).shares, 200);
+    assert_eq!(client.get_queue_position(&second.id), 1);
+
+    // Farmers repay and the queue drains.
+    lend_out(&env, &fund_id, -1660);
+    assert_eq!(client.process_redemptions(&10), 2);
+    assert_eq!(usdc.balance(&first_investor), 1000);
+    assert_eq!(usdc.balance(&second_investor), 1000);
+    assert_eq!(client.get_principal(&second_investor), 0);
+    assert_eq!(client.get_state().total_shares, 1000);
+    assert_eq!(client.process_redemptions(&10), 0);
+}
+
+#[test]
+fn test_cancel_redemption_returns_escrowed_shares() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let (investor, other_investor) = setup_with_yield(&client);
+
+    let first = client.request_redemption(&investor, &300);
+    let second = client.request_redemption(&other_investor, &200);
+    let third = client.request_redemption(&investor, &100);
+
+    assert_eq!(
+        client.try_cancel_redemption(&other_investor, &first.id),
+        Err(Ok(Error::Unauthorized))
+    );
+
+    client.cancel_redemption(&other_investor, &second.id);
+    assert_eq!(client.balance(&other_investor), 1000);
+    assert_eq!(client.get_principal(&other_investor), 1000);
+    assert_eq!(client.get_queue_position(&third.id), 1);
+
+    client.cancel_redemption(&investor, &first.id);
+    assert_eq!(client.balance(&investor), 900);
+    assert_eq!(client.get_principal(&investor), 900);
+    assert_eq!(client.get_queue_position(&third.id), 0);
+
+    advance_time(&env, DEFAULT_NOTICE_PERIOD);
+    assert_eq!(client.process_redemptions(&10), 1);
+    assert_eq!(client.balance(&investor), 900);
+    assert_eq!(client.balance(&fund_id), 0);
+    assert_eq!(client.try_cancel_redemption(&investor, &third.id), Err(Ok(Error::RequestNotFound)));
+}
+
+#[test]
+fn test_instant_redemptions_leave_cash_for_the_queue() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let (investor, other_investor) = setup_with_yield(&client);
+
+    client.request_redemption(&investor, &1000);
+    lend_out(&env, &fund_id, 440);
+
+    // 1150 is set aside for the queued request, so only 350 is free.
+    assert_eq!(
+        client.try_redeem_amount(&other_investor, &351),
+        Err(Ok(Error::InsufficientSeniorBalance))
+    );
+    client.redeem_amount(&other_investor, &350);
+}
+
+#[test]
+fn test_set_notice_period() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let (investor, _) = setup_with_yield(&client);
+
+    let outsider = Address::generate(&env);
+    assert_eq!(client.try_set_notice_period(&outsider, &0), Err(Ok(Error::Unauthorized)));
+
+    client.set_notice_period(&client.get_admin(), &60);
+    assert_eq!(client.get_notice_period(), 60);
+
+    let request = client.request_redemption(&investor, &100);
+    assert_eq!(client.get_expected_fill(&request.id).ready_at, 60);
+    advance_time(&env, 60);
+    assert_eq!(client.process_redemptions(&10), 1);
+}
+
+const YEAR: u64 = 365 * 24 * 60 * 60;
+
+fn set_lockup(client: &FundContractClient, period: u64, early_exit_fee_bps: Option<u32>) {
+    client.set_lockup(
+        &client.get_admin(),
+        &Lockup {
+            period,
+            early_exit_fee_bps,
+        },
+    );
+}
+
+#[test]
+fn test_each_investment_opens_a_lot() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let investor = Address::generate(&env);
+
+    set_lockup(&client, YEAR, None);
+    invest(&client, &investor, 1000);
+    advance_time(&env, 30);
+    invest(&client, &investor, 500);
+
+    assert_eq!(
+        client.get_lots(&investor),
+        vec![
+            &env,
+            Lot {
+                shares: 1000,
+                deposited_at: 0,
+                unlocks_at: YEAR,
+            },
+            Lot {
+                shares: 500,
+                deposited_at: 30,
+                unlocks_at: YEAR + 30,
+            },
+        ]
+    );
+
+    // Unlocked lots are reported as one.
+    advance_time(&env, YEAR + 30);
+    invest(&client, &investor, 100);
+    let lots = client.get_lots(&investor);
+    assert_eq!(lots.len(), 2);
+    assert_eq!(
+        lots.get_unchecked(0),
+        Lot {
+            shares: 1500,
+            deposited_at: 0,
+            unlocks_at: YEAR + 30,
+        }
+    );
+    assert_eq!(lots.get_unchecked(1).unlocks_at, 2 * YEAR + 60);
+}
+
+#[test]
+fn test_lockup_rejects_early_redemption() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let investor = Address::generate(&env);
+
+    set_lockup(&client, YEAR, None);
+    invest(&client, &investor, 1000);
+
+    assert_eq!(client.try_redeem_investment(&investor, &100), Err(Ok(Error::Locked)));
+    assert_eq!(client.try_redeem_amount(&investor, &100), Err(Ok(Error::Locked)));
+    assert_eq!(client.try_request_redemption(&investor, &100), Err(Ok(Error::Locked)));
+
+    advance_time(&env, YEAR);
+    client.redeem_investment(&investor, &100);
+    assert_eq!(client.balance(&investor), 900);
+}
+
+#[test]
+fn test_early_exit_fee_goes_to_reserve() {
+    let (env, fund_id, _, _, usdc_id, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let investor = Address::generate(&env);
+
+    set_lockup(&client, YEAR, Some(200));
+    invest(&client, &investor, 1000);
+    let before = client.get_state();
+
+    let redemption = client.redeem_investment(&investor, &500);
+    assert_eq!(
+        redemption,
+        Redemption {
+            shares: 500,
+            amount_usdc: 490,
+            principal: 500,
+            yield_usdc: -10,
+            early_exit_fee: 10,
+        }
+    );
+    assert_eq!(TokenClient::new(&env, &usdc_id).balance(&investor), 490);
+
+    let after = client.get_state();
+    assert_eq!(after.total_senior, before.total_senior - 500);
+    assert_eq!(after.total_reserve, before.total_reserve + 10);
+}
+
+#[test]
+fn test_unlocked_lots_are_redeemed_first() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let investor = Address::generate(&env);
+
+    invest(&client, &investor, 1000);
+    set_lockup(&client, YEAR, None);
+    invest(&client, &investor, 1000);
+
+    client.redeem_investment(&investor, &1000);
+    assert_eq!(client.try_redeem_investment(&investor, &1), Err(Ok(Error::Locked)));
+    assert_eq!(
+        client.get_lots(&investor),
+        vec![
+            &env,
+            Lot {
+                shares: 1000,
+                deposited_at: 0,
+                unlocks_at: YEAR,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_transferred_shares_stay_locked() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let investor = Address::generate(&env);
+    let receiver = Address::generate(&env);
+
+    set_lockup(&client, YEAR, None);
+    invest(&client, &investor, 1000);
+    client.transfer(&investor, &receiver, &400);
+
+    assert_eq!(client.get_lots(&receiver).get_unchecked(0).unlocks_at, YEAR);
+    assert_eq!(client.get_lots(&investor).get_unchecked(0).shares, 600);
+    assert_eq!(client.try_redeem_investment(&receiver, &400), Err(Ok(Error::Locked)));
+}
+
+#[test]
+fn test_queued_early_exit_pays_fee_up_front() {
+    let (env, fund_id, _, _, usdc_id, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let investor = Address::generate(&env);
+    invest(&client, &Address::generate(&env), 1000);
+
+    set_lockup(&client, YEAR, Some(1000));
+    invest(&client, &investor, 1000);
+    let reserve_before = client.get_state().total_reserve;
+
+    // 10% of 500 shares' value is taken as 50 burned shares.
+    let request = client.request_redemption(&investor, &500);
+    assert_eq!(request.shares, 450);
+    assert_eq!(client.balance(&investor), 500);
+    assert_eq!(client.get_state().total_reserve, reserve_before + 50);
+    assert_eq!(client.get_state().total_shares, 1950);
+
+    // Cancelling hands the shares back free of the lockup they paid to leave.
+    client.cancel_redemption(&investor, &request.id);
+    assert_eq!(client.redeem_investment(&investor, &450).early_exit_fee, 0);
+    assert_eq!(
+        client.get_lots(&investor),
+        vec![
+            &env,
+            Lot {
+                shares: 500,
+                deposited_at: 0,
+                unlocks_at: YEAR,
+            },
+        ]
+    );
+    // The burned fee shares left their value behind for everyone else.
+    assert_eq!(TokenClient::new(&env, &usdc_id).balance(&investor), 2000 * 450 / 1950);
+}
+
+#[test]
+fn test_queued_early_exit_fee_needs_idle_senior_cash() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let investor = Address::generate(&env);
+    set_lockup(&client, YEAR, Some(1000));
+    invest(&client, &investor, 1000);
+
+    // With the senior cash lent out there is nothing to pay the fee from.
+    lend_out(&env, &fund_id, 820);
+    let state = client.get_state();
+    assert_eq!(
+        client.try_request_redemption(&investor, &500),
+        Err(Ok(Error::InsufficientSeniorBalance))
+    );
+    assert_eq!(client.get_state().total_senior, 0);
+    assert_eq!(client.get_state().total_reserve, state.total_reserve);
+    assert_eq!(client.balance(&investor), 1000);
+    assert_eq!(client.get_lots(&investor).get_unchecked(0).shares, 1000);
+}
+
+#[test]
+fn test_shares_without_a_lot_leave_before_locked_lots() {
+    let (env, fund_id, _, _, usdc_id, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let investor = Address::generate(&env);
+
+    // 500 shares from before lots existed, backed by 500 senior USDC.
+    StellarAssetClient::new(&env, &usdc_id).mint(&fund_id, &500);
+    env.as_contract(&fund_id, || {
+        let mut state = load_state(&env).unwrap();
+        share::mint(&env, &mut state, &investor, 500).unwrap();
+        state.total_senior += 500;
+        save_state(&env, &state);
+    });
+    set_lockup(&client, YEAR, None);
+    invest(&client, &investor, 1000);
+
+    let redemption = client.redeem_investment(&investor, &500);
+    assert_eq!(redemption.early_exit_fee, 0);
+    assert_eq!(client.balance(&investor), 1000);
+    assert_eq!(
+        client.get_lots(&investor),
+        vec![
+            &env,
+            Lot {
+                shares: 1000,
+                deposited_at: 0,
+                unlocks_at: YEAR,
+            },
+        ]
+    );
+    assert_eq!(client.try_redeem_investment(&investor, &1), Err(Ok(Error::Locked)));
+}
+
+#[test]
+fn test_set_lockup_validation() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let admin = client.get_admin();
+
+    let lockup = Lockup {
+        period: YEAR,
+        early_exit_fee_bps: Some(10_001),
+    };
+    assert_eq!(client.try_set_lockup(&admin, &lockup), Err(Ok(Error::InvalidLockup)));
+
+    let lockup = Lockup {
+        period: YEAR,
+        early_exit_fee_bps: Some(100),
+    };
+    let outsider = Address::generate(&env);
+    assert_eq!(client.try_set_lockup(&outsider, &lockup), Err(Ok(Error::Unauthorized)));
+
+    client.set_lockup(&admin, &lockup);
+    assert_eq!(client.get_lockup(), lockup);
+}
+
+/// A fund with 1000 senior, 200 subordinated and 100 reserve, 500 of the
+/// senior cash lent out, and `payment` registered as its payment contract.
+fn setup_with_loans(client: &FundContractClient) -> Address {
+    let env = &client.env;
+    invest(client, &Address::generate(env), 1500);
+    env.as_contract(&client.address, || {
+        let mut state = load_state(env).unwrap();
+        state.total_senior = 500;
+        state.loans_receivable = 500;
+        state.total_subordinated = 200;
+        state.total_reserve = 100;
+        save_state(env, &state);
+    });
+
+    let payment = Address::generate(env);
+    client.set_payment_contract(&client.get_admin(), &payment);
+    payment
+}
+
+#[test]
+fn test_write_off_needs_the_payment_contract() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let farmer = Address::generate(&env);
+    let outsider = Address::generate(&env);
+
+    assert_eq!(client.get_payment_contract(), None);
+    assert_eq!(client.try_write_off(&outsider, &farmer, &10), Err(Ok(Error::Unauthorized)));
+
+    let payment = setup_with_loans(&client);
+    assert_eq!(client.get_payment_contract(), Some(payment.clone()));
+    assert_eq!(client.try_write_off(&outsider, &farmer, &10), Err(Ok(Error::Unauthorized)));
+    assert_eq!(client.try_recover(&outsider, &farmer, &10), Err(Ok(Error::Unauthorized)));
+    assert_eq!(client.try_write_off(&payment, &farmer, &0), Err(Ok(Error::InvalidAmount)));
+    assert_eq!(client.try_write_off(&payment, &farmer, &501), Err(Ok(Error::InvalidAmount)));
+}
+
+#[test]
+fn test_reserve_absorbs_small_losses() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let payment = setup_with_loans(&client);
+    let farmer = Address::generate(&env);
+    let nav = client.get_nav();
+
+    let allocation = client.write_off(&payment, &farmer, &80);
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            fund_event(
+                &env,
+                &fund_id,
+                "loss_absorbed",
+                &farmer,
+                LossStep {
+                    tranche: Tranche::Reserve,
+                    amount: 80,
+                    balance: 20,
+                },
+            ),
+        ]
+    );
+    assert_eq!(
+        allocation,
+        LossAllocation {
+            reserve: 80,
+            subordinated: 0,
+            senior: 0,
+        }
+    );
+
+    let state = client.get_state();
+    assert_eq!(state.loans_receivable, 420);
+    assert_eq!(state.total_reserve, 20);
+    assert_eq!(state.total_subordinated, 200);
+    // The reserve's cash stands in for the lost loan.
+    assert_eq!(state.total_senior, 580);
+    assert_eq!(client.get_nav(), nav - 80);
+}
+
+#[test]
+fn test_subordinated_absorbs_what_the_reserve_cannot() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let payment = setup_with_loans(&client);
+    let farmer = Address::generate(&env);
+    let nav = client.get_nav();
+
+    let allocation = client.write_off(&payment, &farmer, &250);
+    let events = env.events().all();
+    assert_eq!(
+        events.slice(events.len() - 2..),
+        vec![
+            &env,
+            fund_event(
+                &env,
+                &fund_id,
+                "loss_absorbed",
+                &farmer,
+                LossStep {
+                    tranche: Tranche::Reserve,
+                    amount: 100,
+                    balance: 0,
+                },
+            ),
+            fund_event(
+                &env,
+                &fund_id,
+                "loss_absorbed",
+                &farmer,
+                LossStep {
+                    tranche: Tranche::Subordinated,
+                    amount: 150,
+                    balance: 50,
+                },
+            ),
+        ]
+    );
+    assert_eq!(
+        allocation,
+        LossAllocation {
+            reserve: 100,
+            subordinated: 150,
+            senior: 0,
+        }
+    );
+
+    // The subordinated XLM stays put; senior gets a claim on it, not cash.
+    let state = client.get_state();
+    assert_eq!(state.total_reserve, 0);
+    assert_eq!(state.total_subordinated, 200);
+    assert_eq!(client.get_senior_claim(), 150);
+    assert_eq!(state.total_senior, 600);
+    assert_eq!(state.loans_receivable, 250);
+    assert_eq!(client.get_nav(), nav - 250);
+}
+
+#[test]
+fn test_senior_takes_losses_beyond_the_lower_tranches() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let payment = setup_with_loans(&client);
+    let first = Address::generate(&env);
+    let second = Address::generate(&env);
+    let nav = client.get_nav();
+
+    client.write_off(&payment, &first, &200);
+    let allocation = client.write_off(&payment, &second, &300);
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            fund_event(
+                &env,
+                &fund_id,
+                "loss_absorbed",
+                &second,
+                LossStep {
+                    tranche: Tranche::Senior,
+                    amount: 200,
+                    balance: 600,
+                },
+            ),
+        ]
+    );
+    assert_eq!(
+        allocation,
+        LossAllocation {
+            reserve: 0,
+            subordinated: 100,
+            senior: 200,
+        }
+    );
+    assert_eq!(
+        client.get_losses(),
+        LossAllocation {
+            reserve: 100,
+            subordinated: 200,
+            senior: 200,
+        }
+    );
+
+    let state = client.get_state();
+    assert_eq!(state.loans_receivable, 0);
+    assert_eq!(state.total_reserve, 0);
+    assert_eq!(state.total_subordinated, 200);
+    assert_eq!(client.get_senior_claim(), 200);
+    assert_eq!(state.total_senior, 600);
+    assert_eq!(client.get_nav(), nav - 500);
+}
+
+#[test]
+fn test_recoveries_repay_senior_first() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let payment = setup_with_loans(&client);
+    let farmer = Address::generate(&env);
+    client.write_off(&payment, &farmer, &500);
+
+    let allocation = client.recover(&payment, &farmer, &250);
+    let events = env.events().all();
+    assert_eq!(
+        events.slice(events.len() - 2..),
+        vec![
+            &env,
+            fund_event(
+                &env,
+                &fund_id,
+                "loss_recovered",
+                &farmer,
+                LossStep {
+                    tranche: Tranche::Senior,
+                    amount: 200,
+                    balance: 800,
+                },
+            ),
+            fund_event(
+                &env,
+                &fund_id,
+                "loss_recovered",
+                &farmer,
+                LossStep {
+                    tranche: Tranche::Subordinated,
+                    amount: 50,
+                    balance: 50,
+                },
+            ),
+        ]
+    );
+    assert_eq!(
+        allocation,
+        LossAllocation {
+            reserve: 0,
+            subordinated: 50,
+            senior: 200,
+        }
+    );
+
+    // The reserve comes last; what nobody lost is senior yield.
+    let yield_before = client.get_state().total_yield;
+    let allocation = client.recover(&payment, &farmer, &300);
+    assert_eq!(
+        allocation,
+        LossAllocation {
+            reserve: 100,
+            subordinated: 150,
+            senior: 50,
+        }
+    );
+    assert_eq!(client.get_losses(), LossAllocation::default());
+
+    let state = client.get_state();
+    assert_eq!(state.total_subordinated, 200);
+    assert_eq!(client.get_senior_claim(), 0);
+    assert_eq!(state.total_reserve, 100);
+    assert_eq!(state.total_senior, 1050);
+    assert_eq!(state.total_yield, yield_before + 50);
+}
+
+#[test]
+fn test_unstaking_into_senior_settles_its_claim() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let operator = Address::generate(&env);
+    client.grant_role(&client.get_admin(), &Role::Operator, &operator);
+    let payment = setup_with_loans(&client);
+    let farmer = Address::generate(&env);
+    client.write_off(&payment, &farmer, &250);
+
+    // 150 of the 200 of subordinated book value is owed to senior.
+    let state = client.get_state();
+    let xlm = state.subordinated_xlm;
+    let recipient = Some(Address::generate(&env));
+    assert_eq!(
+        client.try_unstake_subordinated(&operator, &(xlm / 2), &recipient, &None),
+        Err(Ok(Error::InsufficientSubordinatedBalance))
+    );
+
+    let nav = client.get_nav();
+    let basis = 200 * (xlm / 2) / xlm;
+    let proceeds = client.unstake_subordinated(&operator, &(xlm / 2), &None, &Some(unstake_swap(&client)));
+    assert_eq!(client.get_senior_claim(), 150 - basis);
+    let after = client.get_state();
+    assert_eq!(after.total_senior, state.total_senior + proceeds);
+    assert_eq!(after.total_subordinated, 200 - basis);
+    // Only the swap's price moves NAV; the claim's value isn't counted twice.
+    assert_eq!(client.get_nav(), nav + proceeds - basis);
+
+    // What is left beyond the claim is the tranche's own to pay out.
+    client.unstake_subordinated(&operator, &(xlm / 5), &recipient, &None);
+    assert_eq!(client.get_senior_claim(), 150 - basis);
+}
+
+#[test]
+fn test_draw_capital_lends_idle_senior_cash() {
+    let (env, fund_id, _, _, usdc_id, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    invest(&client, &Address::generate(&env), 1000);
+    let payment = Address::generate(&env);
+    let farmer = Address::generate(&env);
+
+    assert_eq!(client.try_draw_capital(&payment, &farmer, &300), Err(Ok(Error::Unauthorized)));
+    client.set_payment_contract(&client.get_admin(), &payment);
+
+    let nav = client.get_nav();
+    client.draw_capital(&payment, &farmer, &300);
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            fund_event(
+                &env,
+                &fund_id,
+                "capital_drawn",
+                &farmer,
+                CapitalDrawn {
+                    amount_usdc: 300,
+                    loans_receivable: 300,
+                },
+            ),
+        ]
+    );
+    assert_eq!(TokenClient::new(&env, &usdc_id).balance(&farmer), 300);
+
+    let state = client.get_state();
+    assert_eq!(state.total_senior, 520);
+    assert_eq!(state.loans_receivable, 300);
+    assert_eq!(client.get_nav(), nav);
+
+    assert_eq!(
+        client.try_draw_capital(&payment, &farmer, &521),
+        Err(Ok(Error::InsufficientSeniorBalance))
+    );
+}
+
+#[test]
+fn test_repay_capital_returns_principal_and_books_interest() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    invest(&client, &Address::generate(&env), 1000);
+    let payment = Address::generate(&env);
+    let farmer = Address::generate(&env);
+    client.set_payment_contract(&client.get_admin(), &payment);
+    client.draw_capital(&payment, &farmer, &300);
+
+    assert_eq!(client.try_repay_capital(&payment, &farmer, &301, &0), Err(Ok(Error::InvalidAmount)));
+    assert_eq!(client.try_repay_capital(&payment, &farmer, &0, &0), Err(Ok(Error::InvalidAmount)));
+
+    let nav = client.get_nav();
+    mint_usdc(&client, &fund_id, 320);
+    client.repay_capital(&payment, &farmer, &300, &20);
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            fund_event(
+                &env,
+                &fund_id,
+                "capital_repaid",
+                &farmer,
+                CapitalRepaid {
+                    principal: 300,
+                    interest: 20,
+                    loans_receivable: 0,
+                },
+            ),
+        ]
+    );
+
+    let state = client.get_state();
+    assert_eq!(state.total_senior, 840);
+    assert_eq!(state.loans_receivable, 0);
+    assert_eq!(state.total_yield, 20);
+    assert_eq!(client.get_nav(), nav + 20);
+}
+
+/// 2025-12-31T23:59:59Z.
+const END_OF_2025: u64 = 1_767_225_599;
+
+fn donate(client: &FundContractClient, donor: &Address, amount: i128) {
+    mint_usdc(client, donor, amount);
+    client.donate(donor, &amount, &None);
+}
+
+#[test]
+fn test_year_of_timestamp() {
+    assert_eq!(year_of(0), 1970);
+    assert_eq!(year_of(END_OF_2025), 2025);
+    assert_eq!(year_of(END_OF_2025 + 1), 2026);
+    // 2024-02-29T12:00:00Z and 2000-03-01T00:00:00Z.
+    assert_eq!(year_of(1_709_208_000), 2024);
+    assert_eq!(year_of(951_868_800), 2000);
+}
+
+#[test]
+fn test_donations_issue_numbered_receipts() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let donor = Address::generate(&env);
+    env.ledger().with_mut(|ledger| ledger.timestamp = END_OF_2025 - 60);
+
+    donate(&client, &donor, 50);
+    donate(&client, &donor, 70);
+
+    assert_eq!(
+        client.get_donation_receipt(&1),
+        DonationReceipt {
+            id: 1,
+            donor: donor.clone(),
+            amount_usdc: 70,
+            timestamp: END_OF_2025 - 60,
+            beneficiary: None,
+        }
+    );
+    assert_eq!(client.try_get_donation_receipt(&2), Err(Ok(Error::ReceiptNotFound)));
+    assert_eq!(client.try_donate(&donor, &0, &None), Err(Ok(Error::InvalidAmount)));
+}
+
+#[test]
+fn test_donated_part_of_an_investment_gets_a_receipt() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let investor = Address::generate(&env);
+    client.set_allocation(
+        &client.get_admin(),
+        &Allocation {
+            senior_bps: 7000,
+            subordinated_bps: 1500,
+            reserve_bps: 500,
+            donation_bps: 1000,
+        },
+    );
+
+    invest(&client, &investor, 1000);
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            fund_event(
+                &env,
+                &fund_id,
+                "receipt_issued",
+                &investor,
+                DonationReceipt {
+                    id: 0,
+                    donor: investor.clone(),
+                    amount_usdc: 100,
+                    timestamp: 0,
+                    beneficiary: None,
+                },
+            ),
+        ]
+    );
+    assert_eq!(client.get_donation_statement(&investor, &1970).total_usdc, 100);
+}
+
+#[test]
+fn test_donation_statement_covers_one_calendar_year() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let donor = Address::generate(&env);
+    let other = Address::generate(&env);
+
+    env.ledger().with_mut(|ledger| ledger.timestamp = END_OF_2025 - 3600);
+    donate(&client, &donor, 50);
+    donate(&client, &other, 500);
+    env.ledger().with_mut(|ledger| ledger.timestamp = END_OF_2025);
+    donate(&client, &donor, 25);
+    env.ledger().with_mut(|ledger| ledger.timestamp = END_OF_2025 + 1);
+    donate(&client, &donor, 10);
+
+    let statement = client.get_donation_statement(&donor, &2025);
+    assert_eq!(
+        statement,
+        DonationStatement {
+            donor: donor.clone(),
+            year: 2025,
+            total_usdc: 75,
+            receipts: vec![
+                &env,
+                client.get_donation_receipt(&0),
+                client.get_donation_receipt(&2),
+            ],
+        }
+    );
+    assert_eq!(client.get_donation_statement(&donor, &2026).total_usdc, 10);
+    assert_eq!(client.get_donation_statement(&donor, &2024).receipts.len(), 0);
+}
+
+fn donate_to(client: &FundContractClient, donor: &Address, beneficiary: &Address, amount: i128) {
+    mint_usdc(client, donor, amount);
+    client.donate(donor, &amount, &Some(beneficiary.clone()));
+}
+
+#[test]
+fn test_directed_donations_are_escrowed_for_the_beneficiary() {
+    let (env, fund_id, _, _, usdc_id, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let donor = Address::generate(&env);
+    let farmer = Address::generate(&env);
+
+    donate_to(&client, &donor, &farmer, 200);
+    assert_eq!(client.get_donation_receipt(&0).beneficiary, Some(farmer.clone()));
+    advance_time(&env, 10);
+    donate_to(&client, &donor, &farmer, 50);
+
+    assert_eq!(client.get_state().total_fund, 0);
+    assert_eq!(
+        client.get_escrow(&farmer),
+        vec![
+            &env,
+            EscrowedDonation {
+                donor: donor.clone(),
+                amount_usdc: 250,
+                claimable_until: DEFAULT_CLAIM_WINDOW + 10,
+            },
+        ]
+    );
+
+    assert_eq!(client.claim_donations(&farmer, &farmer), 250);
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            fund_event(
+                &env,
+                &fund_id,
+                "donation_claimed",
+                &farmer,
+                DonationClaimed {
+                    claimant: farmer.clone(),
+                    amount_usdc: 250,
+                },
+            ),
+        ]
+    );
+    assert_eq!(TokenClient::new(&env, &usdc_id).balance(&farmer), 250);
+    assert_eq!(client.get_escrow(&farmer).len(), 0);
+    assert_eq!(client.try_claim_donations(&farmer, &farmer), Err(Ok(Error::NothingToClaim)));
+}
+
+#[test]
+fn test_escrow_holds_a_bounded_number_of_donors() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let farmer = Address::generate(&env);
+
+    for _ in 0..MAX_ESCROWED_DONORS {
+        donate_to(&client, &Address::generate(&env), &farmer, 1);
+    }
+    let late_donor = Address::generate(&env);
+    mint_usdc(&client, &late_donor, 100);
+    assert_eq!(
+        client.try_donate(&late_donor, &100, &Some(farmer.clone())),
+        Err(Ok(Error::EscrowFull))
+    );
+
+    // Donors already in escrow still add to their entries.
+    let first_donor = client.get_escrow(&farmer).get_unchecked(0).donor;
+    donate_to(&client, &first_donor, &farmer, 100);
+    let escrow = client.get_escrow(&farmer);
+    assert_eq!(escrow.len(), MAX_ESCROWED_DONORS);
+    assert_eq!(escrow.get_unchecked(0).amount_usdc, 101);
+
+    // An expired entry isn't topped up, and releasing it frees the slot.
+    advance_time(&env, DEFAULT_CLAIM_WINDOW + 1);
+    assert_eq!(
+        client.try_donate(&first_donor, &100, &Some(farmer.clone())),
+        Err(Ok(Error::EscrowFull))
+    );
+    client.release_expired_donations(&farmer);
+    client.donate(&late_donor, &100, &Some(farmer.clone()));
+    assert_eq!(client.get_escrow(&farmer).len(), 1);
+}
+
+#[test]
+fn test_disburser_claims_for_a_beneficiary() {
+    let (env, fund_id, _, _, usdc_id, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let cooperative = Address::generate(&env);
+    let disburser = Address::generate(&env);
+    donate_to(&client, &Address::generate(&env), &cooperative, 300);
+
+    assert_eq!(
+        client.try_claim_donations(&disburser, &cooperative),
+        Err(Ok(Error::Unauthorized))
+    );
+
+    client.grant_role(&client.get_admin(), &Role::Disburser, &disburser);
+    assert_eq!(client.claim_donations(&disburser, &cooperative), 300);
+    assert_eq!(TokenClient::new(&env, &usdc_id).balance(&disburser), 300);
+}
+
+#[test]
+fn test_unclaimed_donations_fall_back_to_the_pool() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let donor = Address::generate(&env);
+    let farmer = Address::generate(&env);
+
+    let other_donor = Address::generate(&env);
+
+    client.set_claim_window(&client.get_admin(), &100);
+    donate_to(&client, &donor, &farmer, 200);
+    advance_time(&env, 60);
+    donate_to(&client, &other_donor, &farmer, 50);
+
+    // Still inside the window, nothing moves.
+    assert_eq!(client.release_expired_donations(&farmer), 0);
+
+    advance_time(&env, 41);
+    assert_eq!(client.release_expired_donations(&farmer), 200);
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            fund_event(&env, &fund_id, "donation_released", &farmer, DonationReleased { amount_usdc: 200 }),
+        ]
+    );
+    assert_eq!(client.get_state().total_fund, 200);
+
+    // A late claim only gets what is still in its window, and sweeps the rest.
+    advance_time(&env, 60);
+    donate_to(&client, &donor, &farmer, 30);
+    assert_eq!(client.claim_donations(&farmer, &farmer), 30);
+    assert_eq!(client.get_state().total_fund, 250);
+    assert_eq!(client.get_claim_window(), 100);
+}
+
+const MONTH: u64 = 30 * 24 * 60 * 60;
+
+/// A fund with `pool` USDC of donations, paying `share_bps` of interest
+/// up to the given caps, and a registered payment contract, which it returns.
+fn setup_subsidy(client: &FundContractClient, pool: i128, share_bps: u32, loan_cap: i128, period_cap: i128) -> Address {
+    let env = &client.env;
+    let admin = client.get_admin();
+    if pool > 0 {
+        donate(client, &Address::generate(env), pool);
+    }
+    client.set_subsidy(
+        &admin,
+        &Subsidy {
+            share_bps,
+            loan_cap,
+            period_cap,
+            period: MONTH,
+        },
+    );
+    let payment = Address::generate(env);
+    client.set_payment_contract(&admin, &payment);
+    payment
+}
+
+#[test]
+fn test_set_subsidy_validation() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let admin = client.get_admin();
+    let subsidy = Subsidy {
+        share_bps: 5000,
+        loan_cap: 100,
+        period_cap: 1000,
+        period: MONTH,
+    };
+
+    for invalid in [
+        Subsidy {
+            share_bps: 10_001,
+            ..subsidy.clone()
+        },
+        Subsidy {
+            loan_cap: -1,
+            ..subsidy.clone()
+        },
+        Subsidy {
+            period: 0,
+            ..subsidy.clone()
+        },
+    ] {
+        assert_eq!(client.try_set_subsidy(&admin, &invalid), Err(Ok(Error::InvalidSubsidy)));
+    }
+    assert_eq!(
+        client.try_set_subsidy(&Address::generate(&env), &subsidy),
+        Err(Ok(Error::Unauthorized))
+    );
+
+    assert_eq!(client.get_subsidy().share_bps, 0);
+    client.set_subsidy(&admin, &subsidy);
+    assert_eq!(client.get_subsidy(), subsidy);
+}
+
+#[test]
+fn test_subsidy_moves_donations_into_senior_yield() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let payment = setup_subsidy(&client, 1000, 5000, 100, 1000);
+    let farmer = Address::generate(&env);
+
+    assert_eq!(
+        client.try_subsidize_interest(&Address::generate(&env), &farmer, &0, &80),
+        Err(Ok(Error::Unauthorized))
+    );
+
+    let before = client.get_state();
+    assert_eq!(client.subsidize_interest(&payment, &farmer, &0, &80), 40);
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            fund_event(
+                &env,
+                &fund_id,
+                "interest_subsidized",
+                &farmer,
+                InterestSubsidized {
+                    interest: 80,
+                    subsidy: 40,
+                    pool: 960,
+                },
+            ),
+        ]
+    );
+
+    let after = client.get_state();
+    assert_eq!(after.total_fund, before.total_fund - 40);
+    assert_eq!(after.total_senior, before.total_senior + 40);
+    assert_eq!(after.total_yield, before.total_yield + 40);
+}
+
+#[test]
+fn test_subsidy_caps_per_loan_and_period() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let payment = setup_subsidy(&client, 1000, 5000, 100, 150);
+    let first = Address::generate(&env);
+    let second = Address::generate(&env);
+
+    assert_eq!(client.subsidize_interest(&payment, &first, &0, &80), 40);
+    assert_eq!(client.subsidize_interest(&payment, &first, &0, &80), 40);
+    assert_eq!(client.subsidize_interest(&payment, &first, &0, &80), 20);
+    assert_eq!(client.subsidize_interest(&payment, &first, &0, &80), 0);
+    assert_eq!(client.get_loan_subsidy(&first, &0), 100);
+
+    // A later loan of the same farmer has a cap of its own, but the
+    // period's cap is shared by every loan.
+    assert_eq!(client.subsidize_interest(&payment, &first, &10, &80), 40);
+    assert_eq!(client.subsidize_interest(&payment, &second, &0, &80), 10);
+    assert_eq!(client.get_period_subsidy(), 150);
+    assert_eq!(client.subsidize_interest(&payment, &second, &0, &80), 0);
+
+    advance_time(&env, MONTH);
+    assert_eq!(client.get_period_subsidy(), 0);
+    assert_eq!(client.subsidize_interest(&payment, &second, &0, &80), 40);
+}
+
+#[test]
+fn test_subsidy_never_exceeds_the_pool() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let payment = setup_subsidy(&client, 30, 5000, 1000, 1000);
+    let farmer = Address::generate(&env);
+
+    assert_eq!(client.subsidize_interest(&payment, &farmer, &0, &80), 30);
+    assert_eq!(client.get_state().total_fund, 0);
+    assert_eq!(client.subsidize_interest(&payment, &farmer, &0, &80), 0);
+}
+
+fn donate_to_guarantee(client: &FundContractClient, donor: &Address, amount: i128) {
+    mint_usdc(client, donor, amount);
+    client.donate_to_guarantee(donor, &amount);
+}
+
+/// `setup_with_loans` with `pool` USDC in the guarantee and `farmer` in a
+/// cohort with the given caps. Returns the payment contract.
+fn setup_guarantee(client: &FundContractClient, farmer: &Address, pool: i128, loan_cap: i128, cohort_cap: i128) -> Address {
+    let env = &client.env;
+    let admin = client.get_admin();
+    let payment = setup_with_loans(client);
+    donate_to_guarantee(client, &Address::generate(env), pool);
+    let cohort = Symbol::new(env, "smallholders");
+    client.set_guarantee_cohort(&admin, &cohort, &loan_cap, &cohort_cap);
+    client.set_farmer_cohort(&admin, farmer, &Some(cohort));
+    payment
+}
+
+#[test]
+fn test_guarantee_donations_get_a_receipt() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let donor = Address::generate(&env);
+
+    assert_eq!(client.try_donate_to_guarantee(&donor, &0), Err(Ok(Error::InvalidAmount)));
+
+    let nav = client.get_nav();
+    donate_to_guarantee(&client, &donor, 300);
+    let events = env.events().all();
+    assert_eq!(
+        events.slice(events.len() - 2..events.len() - 1),
+        vec![
+            &env,
+            fund_event(
+                &env,
+                &fund_id,
+                "guarantee_donated",
+                &donor,
+                GuaranteeDonated {
+                    amount_usdc: 300,
+                    pool: 300,
+                },
+            ),
+        ]
+    );
+    assert_eq!(client.get_guarantee_pool(), 300);
+    assert_eq!(client.get_donation_statement(&donor, &1970).total_usdc, 300);
+    // The pool belongs to neither the investors nor the general pool.
+    assert_eq!(client.get_nav(), nav);
+    assert_eq!(client.get_state().total_fund, 0);
+}
+
+#[test]
+fn test_guarantee_cohort_setup() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let admin = client.get_admin();
+    let outsider = Address::generate(&env);
+    let farmer = Address::generate(&env);
+    let cohort = Symbol::new(&env, "smallholders");
+
+    assert_eq!(
+        client.try_set_guarantee_cohort(&admin, &cohort, &-1, &100),
+        Err(Ok(Error::InvalidCoverage))
+    );
+    assert_eq!(
+        client.try_set_guarantee_cohort(&outsider, &cohort, &50, &100),
+        Err(Ok(Error::Unauthorized))
+    );
+    assert_eq!(
+        client.try_set_farmer_cohort(&admin, &farmer, &Some(cohort.clone())),
+        Err(Ok(Error::CohortNotFound))
+    );
+    assert_eq!(client.try_get_guarantee_coverage(&cohort), Err(Ok(Error::CohortNotFound)));
+
+    client.set_guarantee_cohort(&admin, &cohort, &50, &100);
+    client.set_farmer_cohort(&admin, &farmer, &Some(cohort.clone()));
+    assert_eq!(client.get_farmer_cohort(&farmer), Some(cohort.clone()));
+    assert_eq!(
+        client.try_set_farmer_cohort(&outsider, &farmer, &None),
+        Err(Ok(Error::Unauthorized))
+    );
+    client.set_farmer_cohort(&admin, &farmer, &None);
+    assert_eq!(client.get_farmer_cohort(&farmer), None);
+}
+
+#[test]
+fn test_guarantee_covers_first_losses_up_to_its_caps() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let farmer = Address::generate(&env);
+    let payment = setup_guarantee(&client, &farmer, 300, 200, 250);
+    let cohort = Symbol::new(&env, "smallholders");
+
+    assert_eq!(
+        client.try_claim_guarantee(&Address::generate(&env), &farmer, &100),
+        Err(Ok(Error::Unauthorized))
+    );
+    assert_eq!(client.try_claim_guarantee(&payment, &farmer, &501), Err(Ok(Error::InvalidAmount)));
+
+    let before = client.get_state();
+    let nav = client.get_nav();
+    assert_eq!(client.claim_guarantee(&payment, &farmer, &300), 200);
+    let claim = GuaranteeClaim {
+        farmer: farmer.clone(),
+        cohort: cohort.clone(),
+        loss: 300,
+        covered: 200,
+        timestamp: 0,
+    };
+    assert_eq!(
+        last_event(&env),
+        vec![&env, fund_event(&env, &fund_id, "guarantee_claimed", &farmer, claim.clone())]
+    );
+    let after = client.get_state();
+    assert_eq!(after.loans_receivable, before.loans_receivable - 200);
+    assert_eq!(after.total_senior, before.total_senior + 200);
+    assert_eq!(client.get_nav(), nav);
+    assert_eq!(client.get_guarantee_claims(&farmer), vec![&env, claim]);
+
+    // The next loan in the cohort only gets what is left of its cap.
+    let neighbour = Address::generate(&env);
+    client.set_farmer_cohort(&client.get_admin(), &neighbour, &Some(cohort.clone()));
+    assert_eq!(client.claim_guarantee(&payment, &neighbour, &100), 50);
+    assert_eq!(
+        client.get_guarantee_coverage(&cohort),
+        CohortCoverage {
+            cohort: cohort.clone(),
+            loan_cap: 200,
+            cohort_cap: 250,
+            used: 250,
+            remaining: 0,
+            claims: 2,
+        }
+    );
+    assert_eq!(client.claim_guarantee(&payment, &neighbour, &100), 0);
+
+    // Raising the cohort's cap leaves it the rest of the pool.
+    client.set_guarantee_cohort(&client.get_admin(), &cohort, &200, &1000);
+    assert_eq!(client.get_guarantee_coverage(&cohort).remaining, 50);
+
+    // Farmers outside any cohort aren't covered.
+    assert_eq!(client.claim_guarantee(&payment, &Address::generate(&env), &100), 0);
+    assert_eq!(client.get_guarantee_pool(), 50);
+}
+
+#[test]
+fn test_recoveries_refill_the_guarantee_last() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let farmer = Address::generate(&env);
+    let payment = setup_guarantee(&client, &farmer, 300, 200, 1000);
+
+    assert_eq!(client.claim_guarantee(&payment, &farmer, &300), 200);
+    client.write_off(&payment, &farmer, &100);
+    assert_eq!(client.get_losses().reserve, 100);
+    assert_eq!(client.get_guarantee_pool(), 100);
+
+    let allocation = client.recover(&payment, &farmer, &250);
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            fund_event(
+                &env,
+                &fund_id,
+                "guarantee_recovered",
+                &farmer,
+                GuaranteeRecovered {
+                    amount_usdc: 150,
+                    pool: 250,
+                },
+            ),
+        ]
+    );
+    assert_eq!(allocation.reserve, 100);
+    assert_eq!(client.get_losses(), LossAllocation::default());
+
+    // Once the guarantee is whole too, the rest is senior yield.
+    let yield_before = client.get_state().total_yield;
+    client.recover(&payment, &farmer, &100);
+    assert_eq!(client.get_guarantee_pool(), 300);
+    assert_eq!(client.get_state().total_yield, yield_before + 50);
+}
+
+/// Mints `cap` to a new sponsor and opens a campaign with it running from
+/// `starts_at` for 1000 seconds. Returns the sponsor and the campaign id.
+fn create_campaign(client: &FundContractClient, match_bps: u32, cap: i128, starts_at: u64) -> (Address, u64) {
+    let sponsor = Address::generate(&client.env);
+    mint_usdc(client, &sponsor, cap);
+    let id = client.create_campaign(&client.get_admin(), &sponsor, &match_bps, &cap, &starts_at, &(starts_at + 1000));
+    (sponsor, id)
+}
+
+#[test]
+fn test_create_campaign_escrows_the_cap() {
+    let (env, fund_id, _, _, usdc_id, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let sponsor = Address::generate(&env);
+    advance_time(&env, 100);
+
+    for (match_bps, cap, starts_at, ends_at) in [(0, 500, 100, 200), (10_000, 0, 100, 200), (10_000, 500, 200, 200), (10_000, 500, 0, 100)] {
+        assert_eq!(
+            client.try_create_campaign(&client.get_admin(), &sponsor, &match_bps, &cap, &starts_at, &ends_at),
+            Err(Ok(Error::InvalidCampaign))
+        );
+    }
+    assert_eq!(client.try_get_campaign(&0), Err(Ok(Error::CampaignNotFound)));
+
+    mint_usdc(&client, &sponsor, 500);
+    let id = client.create_campaign(&client.get_admin(), &sponsor, &10_000, &500, &100, &200);
+    let campaign = Campaign {
+        id,
+        sponsor: sponsor.clone(),
+        match_bps: 10_000,
+        cap: 500,
+        starts_at: 100,
+        ends_at: 200,
+        matched: 0,
+        donations: 0,
+        closed: false,
+    };
+    assert_eq!(
+        last_event(&env),
+        vec![&env, fund_event(&env, &fund_id, "campaign_created", &sponsor, campaign.clone())]
+    );
+    assert_eq!(client.get_campaign(&id), campaign.clone());
+    assert_eq!(client.get_open_campaigns(), vec![&env, campaign]);
+    assert_eq!(TokenClient::new(&env, &usdc_id).balance(&sponsor), 0);
+    // Match money isn't donated until it matches a donation.
+    assert_eq!(client.get_state().total_fund, 0);
+}
+
+#[test]
+fn test_campaign_matches_donations_while_it_runs() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let (sponsor, id) = create_campaign(&client, 10_000, 150, 100);
+    let donor = Address::generate(&env);
+
+    donate(&client, &donor, 50);
+    assert_eq!(client.get_state().total_fund, 50);
+
+    advance_time(&env, 100);
+    donate(&client, &donor, 100);
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            fund_event(
+                &env,
+                &fund_id,
+                "donation_matched",
+                &donor,
+                DonationMatched {
+                    campaign: id,
+                    sponsor: sponsor.clone(),
+                    amount_usdc: 100,
+                },
+            ),
+        ]
+    );
+    assert_eq!(client.get_state().total_fund, 250);
+
+    // The last donation only gets what is left of the cap.
+    donate(&client, &donor, 100);
+    assert_eq!(client.get_state().total_fund, 400);
+    assert_eq!(
+        client.get_campaign_progress(&id),
+        CampaignProgress {
+            id,
+            matched: 150,
+            remaining: 0,
+            donations: 2,
+            active: false,
+        }
+    );
+}
+
+#[test]
+fn test_campaigns_match_directed_donations_into_escrow() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let (first, _) = create_campaign(&client, 5000, 1000, 0);
+    let (second, _) = create_campaign(&client, 2000, 1000, 0);
+    let donor = Address::generate(&env);
+    let beneficiary = Address::generate(&env);
+
+    donate_to(&client, &donor, &beneficiary, 100);
+    let escrow = client.get_escrow(&beneficiary);
+    assert_eq!(escrow.len(), 3);
+    assert_eq!(escrow.get_unchecked(0).donor, donor);
+    assert_eq!((escrow.get_unchecked(1).donor, escrow.get_unchecked(1).amount_usdc), (first, 50));
+    assert_eq!((escrow.get_unchecked(2).donor, escrow.get_unchecked(2).amount_usdc), (second, 20));
+    assert_eq!(client.get_state().total_fund, 0);
+}
+
+#[test]
+fn test_close_campaign_refunds_unused_match_money() {
+    let (env, fund_id, _, _, usdc_id, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+    let (sponsor, id) = create_campaign(&client, 10_000, 500, 0);
+    donate(&client, &Address::generate(&env), 100);
+
+    assert_eq!(client.try_close_campaign(&id), Err(Ok(Error::CampaignNotEnded)));
+    assert_eq!(client.try_close_campaign(&7), Err(Ok(Error::CampaignNotFound)));
+
+    advance_time(&env, 1001);
+    donate(&client, &Address::generate(&env), 100);
+    assert!(!client.get_campaign_progress(&id).active);
+
+    assert_eq!(client.close_campaign(&id), 400);
+    let events = env.events().all();
+    assert_eq!(
+        events.slice(events.len() - 2..events.len() - 1),
+        vec![
+            &env,
+            fund_event(
+                &env,
+                &fund_id,
+                "campaign_closed",
+                &sponsor,
+                CampaignClosed {
+                    id,
+                    matched: 100,
+                    refunded: 400,
+                },
+            ),
+        ]
+    );
+    assert_eq!(TokenClient::new(&env, &usdc_id).balance(&sponsor), 400);
+    assert_eq!(client.get_donation_statement(&sponsor, &1970).total_usdc, 100);
+    assert_eq!(client.get_state().total_fund, 300);
+
+    assert!(client.get_campaign(&id).closed);
+    assert_eq!(client.get_open_campaigns(), vec![&env]);
+    assert_eq!(client.try_close_campaign(&id), Err(Ok(Error::CampaignClosed)));
+}
+
+#[test]
+fn test_campaigns_need_an_admin_and_a_free_slot() {
+    let (env, fund_id, _, _, _, _) = setup();
+    let client = FundContractClient::new(&env, &fund_id);
+
+    // A sponsor can't approve their own campaign.
+    let sponsor = Address::generate(&env);
+    mint_usdc(&client, &sponsor, 500);
+    assert_eq!(
+        client.try_create_campaign(&sponsor, &sponsor, &10_000, &500, &0, &1000),
+        Err(Ok(Error::Unauthorized))
+    );
+
+    for _ in 0..MAX_OPEN_CAMPAIGNS {
+        create_campaign(&client, 10_000, 500, 0);
+    }
+    assert_eq!(
+        client.try_create_campaign(&client.get_admin(), &sponsor, &10_000, &500, &0, &1000),
+        Err(Ok(Error::TooManyCampaigns))
+    );
+    assert_eq!(client.get_open_campaigns().len(), MAX_OPEN_CAMPAIGNS);
+
+    // Closing an ended campaign frees its slot.
+    advance_time(&env, 1001);
+    client.close_campaign(&0);
+    create_campaign(&client, 10_000, 500, 1001);
+    assert_eq!(client.get_open_campaigns().len(), MAX_OPEN_CAMPAIGNS);
+}
@@ -0,0 +1,40 @@
+//! TTL policy for the fund's ledger entries.
+//!
+//! The instance, which holds the fund aggregates, is bumped on every call.
+//! Persistent entries (config, roles and investor balances) are bumped
+//! whenever they are read or written, and admins can bump idle ones with
+//! `bump_all` and `extend_balance_ttl`.
+
+use soroban_sdk::{Env, IntoVal, Val};
+
+pub const DAY_IN_LEDGERS: u32 = 17_280;
+
+/// The instance lives a week past its last use.
+pub const INSTANCE_BUMP_AMOUNT: u32 = 7 * DAY_IN_LEDGERS;
+pub const INSTANCE_LIFETIME_THRESHOLD: u32 = INSTANCE_BUMP_AMOUNT - DAY_IN_LEDGERS;
+
+/// Persistent entries live about a month past their last use.
+pub const PERSISTENT_BUMP_AMOUNT: u32 = 30 * DAY_IN_LEDGERS;
+pub const PERSISTENT_LIFETIME_THRESHOLD: u32 = PERSISTENT_BUMP_AMOUNT - DAY_IN_LEDGERS;
+
+pub fn extend_instance(env: &Env) {
+    env.storage()
+        .instance()
+        .extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+}
+
+/// Bumps a persistent entry that is known to exist.
+pub fn extend_persistent<K: IntoVal<Env, Val>>(env: &Env, key: &K) {
+    env.storage()
+        .persistent()
+        .extend_ttl(key, PERSISTENT_LIFETIME_THRESHOLD, PERSISTENT_BUMP_AMOUNT);
+}
+
+/// Bumps a persistent entry if it exists. Returns whether it did.
+pub fn extend_persistent_if_present<K: IntoVal<Env, Val>>(env: &Env, key: &K) -> bool {
+    let present = env.storage().persistent().has(key);
+    if present {
+        extend_persistent(env, key);
+    }
+    present
+}
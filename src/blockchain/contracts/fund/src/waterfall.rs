@@ -0,0 +1,159 @@
+//! Loss waterfall for written-off loans.
+//!
+//! A loan the payment contract writes off takes the capital still lent out
+//! on it out of `loans_receivable`, and with it out of NAV. Which tranche bears the loss
+//! is decided here: the reserve first, then the subordinated tranche, and
+//! the senior tranche only for what those two can't cover. The reserve
+//! makes senior whole in cash. The subordinated tranche is XLM collateral
+//! in Blend, so what it absorbs becomes senior's claim on that collateral:
+//! the XLM stays where it is, but unstaking it into senior settles the
+//! claim, and it can't be paid out anywhere else.
+//!
+//! Recoveries on written-off loans flow back the other way: senior is made
+//! whole first, then the subordinated tranche, whose part settles senior's
+//! claim in cash, then the reserve. Anything recovered beyond the losses
+//! still on record is senior yield.
+
+use soroban_sdk::{contracttype, Address, Env};
+
+use crate::{events, FundState};
+
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Tranche {
+    Reserve,
+    Subordinated,
+    Senior,
+}
+
+/// USDC per tranche, for one write-off or recovery or for the losses
+/// that haven't been recovered yet.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[contracttype]
+pub struct LossAllocation {
+    pub reserve: i128,
+    pub subordinated: i128,
+    pub senior: i128,
+}
+
+#[derive(Clone)]
+#[contracttype]
+pub enum WaterfallKey {
+    Losses,
+    SeniorClaim,
+}
+
+/// Losses each tranche has absorbed and not yet had back.
+pub fn outstanding(env: &Env) -> LossAllocation {
+    env.storage()
+        .instance()
+        .get(&WaterfallKey::Losses)
+        .unwrap_or_default()
+}
+
+fn write_outstanding(env: &Env, losses: &LossAllocation) {
+    env.storage().instance().set(&WaterfallKey::Losses, losses);
+}
+
+/// Part of the subordinated tranche's book value that is owed to senior
+/// for losses it absorbed.
+pub fn senior_claim(env: &Env) -> i128 {
+    env.storage().instance().get(&WaterfallKey::SeniorClaim).unwrap_or(0)
+}
+
+fn write_senior_claim(env: &Env, claim: i128) {
+    env.storage().instance().set(&WaterfallKey::SeniorClaim, &claim);
+}
+
+/// Subordinated book value that is still the tranche's own.
+pub fn free_subordinated(env: &Env, state: &FundState) -> i128 {
+    state.total_subordinated - senior_claim(env)
+}
+
+/// Settles senior's claim with `amount` of subordinated book value that was
+/// unstaked into senior.
+pub fn settle(env: &Env, amount: i128) {
+    let claim = senior_claim(env);
+    write_senior_claim(env, claim - amount.min(claim).max(0));
+}
+
+/// Books a loss of `amount` on `farmer`'s loan against the tranches.
+/// The caller makes sure `amount` is no more than `loans_receivable`.
+pub fn absorb(env: &Env, state: &mut FundState, farmer: &Address, amount: i128) -> LossAllocation {
+    state.loans_receivable -= amount;
+
+    let reserve = amount.min(state.total_reserve.max(0));
+    state.total_reserve -= reserve;
+    state.total_senior += reserve;
+    step(env, "loss_absorbed", farmer, Tranche::Reserve, reserve, state.total_reserve);
+
+    let subordinated = (amount - reserve).min(free_subordinated(env, state).max(0));
+    write_senior_claim(env, senior_claim(env) + subordinated);
+    step(env, "loss_absorbed", farmer, Tranche::Subordinated, subordinated, free_subordinated(env, state));
+
+    let senior = amount - reserve - subordinated;
+    step(env, "loss_absorbed", farmer, Tranche::Senior, senior, state.total_senior);
+
+    let allocation = LossAllocation {
+        reserve,
+        subordinated,
+        senior,
+    };
+    let mut losses = outstanding(env);
+    losses.reserve += reserve;
+    losses.subordinated += subordinated;
+    losses.senior += senior;
+    write_outstanding(env, &losses);
+    allocation
+}
+
+/// Books `amount` recovered on `farmer`'s written-off loan, which the fund
+/// has already received in USDC.
+pub fn recover(env: &Env, state: &mut FundState, farmer: &Address, amount: i128) -> LossAllocation {
+    let mut losses = outstanding(env);
+
+    let recovered = amount.min(losses.senior);
+    let subordinated = (amount - recovered).min(losses.subordinated);
+    let reserve = (amount - recovered - subordinated).min(losses.reserve);
+    let excess = amount - recovered - subordinated - reserve;
+    let senior = recovered + excess;
+
+    losses.senior -= recovered;
+    state.total_senior += senior;
+    state.total_yield += excess;
+    step(env, "loss_recovered", farmer, Tranche::Senior, senior, state.total_senior);
+
+    // The cash lands in senior instead of the XLM it had a claim on. If
+    // the XLM already went to senior, the cash stays there all the same.
+    losses.subordinated -= subordinated;
+    state.total_senior += subordinated;
+    settle(env, subordinated);
+    step(env, "loss_recovered", farmer, Tranche::Subordinated, subordinated, free_subordinated(env, state));
+
+    losses.reserve -= reserve;
+    state.total_reserve += reserve;
+    step(env, "loss_recovered", farmer, Tranche::Reserve, reserve, state.total_reserve);
+
+    write_outstanding(env, &losses);
+    LossAllocation {
+        reserve,
+        subordinated,
+        senior,
+    }
+}
+
+fn step(env: &Env, action: &str, farmer: &Address, tranche: Tranche, amount: i128, balance: i128) {
+    if amount == 0 {
+        return;
+    }
+    events::publish(
+        env,
+        action,
+        farmer,
+        events::LossStep {
+            tranche,
+            amount,
+            balance,
+        },
+    );
+}
@@ -0,0 +1,21 @@
+use soroban_sdk::contracterror;
+
+/// Failure codes returned by the payment contract.
+///
+/// The numeric values are part of the public interface: clients match on
+/// them through the `try_*` methods, so existing codes must never change.
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum Error {
+    NotInitialized = 1,
+    InvalidAmount = 2,
+    LoanRequestNotFound = 3,
+    LoanNotFound = 4,
+    AlreadyApproved = 5,
+    AlreadyInitialized = 6,
+    Unauthorized = 7,
+    Paused = 8,
+    FundNotSet = 9,
+    LoanAlreadyExists = 10,
+}
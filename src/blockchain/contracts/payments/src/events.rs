@@ -0,0 +1,125 @@
+//! Events published by the payment contract.
+//!
+//! Every state change publishes one event. Its topics are
+//! `(CONTRACT, action, subject)`: the contract family, what happened, and the
+//! account it happened to or that made it happen, so indexers can filter on
+//! any of them. The data is `(version, payload)` with one of the typed
+//! payloads below; the action name says which. Each action's payload is
+//! versioned on its own (see `version`).
+//!
+//! | action             | subject | payload             | v |
+//! |--------------------|---------|---------------------|---|
+//! | `initialized`      | admin   | `ContractAddresses` | 1 |
+//! | `credit_requested` | farmer  | `CreditRequested`   | 1 |
+//! | `credit_accepted`  | farmer  | `CreditAccepted`    | 1 |
+//! | `installment_paid` | farmer  | `InstallmentPaid`   | 2 |
+//! | `loan_paid_off`    | farmer  | `LoanPaidOff`       | 1 |
+//! | `loan_written_off` | farmer  | `LoanWrittenOff`    | 2 |
+//! | `loan_recovered`   | farmer  | `LoanRecovered`     | 1 |
+//! | `fund_set`         | admin   | fund `Address`      | 1 |
+//! | `pauser_updated`   | account | `PauserUpdated`     | 1 |
+//! | `pause_updated`    | pauser  | `PauseUpdated`      | 1 |
+//! | `upgraded`         | admin   | wasm hash `BytesN`  | 1 |
+//! | `migrated`         | admin   | `Migrated`          | 1 |
+
+use soroban_sdk::{contracttype, symbol_short, Address, Env, IntoVal, Symbol, Val};
+
+use crate::PauseScope;
+
+pub const CONTRACT: Symbol = symbol_short!("payment");
+
+/// Version of `action`'s payload, bumped whenever it changes shape or
+/// meaning. Actions not listed are on version 1.
+///
+/// - `installment_paid`: 2 reports the part of the interest the fund
+///   subsidized.
+/// - `loan_written_off`: 2 reports the part of the loss the fund's
+///   guarantee covered.
+pub fn version(action: &str) -> u32 {
+    match action {
+        "installment_paid" | "loan_written_off" => 2,
+        _ => 1,
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct CreditRequested {
+    pub amount: i128,
+    pub interest_rate: i128,
+    pub loan_fee: i128,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct CreditAccepted {
+    pub principal: i128,
+    pub disbursed: i128,
+    pub interest_rate: i128,
+    pub term_in_months: u32,
+}
+
+/// `interest` is the contractual interest, of which the fund's donation
+/// pool covered `subsidy`. `remaining_principal` is zero once the
+/// installment settles the loan.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct InstallmentPaid {
+    pub amount: i128,
+    pub interest: i128,
+    pub subsidy: i128,
+    pub remaining_principal: i128,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct LoanPaidOff {
+    pub amount: i128,
+}
+
+/// `loss` is the unpaid principal, of which the fund's first-loss
+/// guarantee covered `guaranteed`; the fund's tranches took the rest.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct LoanWrittenOff {
+    pub loss: i128,
+    pub guaranteed: i128,
+}
+
+/// `outstanding` is what is still unrecovered after this payment.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct LoanRecovered {
+    pub payer: Address,
+    pub amount: i128,
+    pub outstanding: i128,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct PauserUpdated {
+    pub admin: Address,
+    pub granted: bool,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct PauseUpdated {
+    pub scope: PauseScope,
+    pub paused: bool,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct Migrated {
+    pub version: u32,
+    pub migrated: u32,
+}
+
+pub fn publish<D: IntoVal<Env, Val>>(env: &Env, action: &str, subject: &Address, payload: D) {
+    let payload: Val = payload.into_val(env);
+    env.events().publish(
+        (CONTRACT, Symbol::new(env, action), subject.clone()),
+        (version(action), payload),
+    );
+}
@@ -1,8 +1,20 @@
 #![no_std]
 use soroban_sdk::{
-    contract, contractimpl, contracttype, Address, Env, Symbol, vec, IntoVal
+    contract, contractimpl, contracttype, Address, BytesN, Env, Symbol, Val, Vec, vec, IntoVal
 };
 
+mod error;
+mod events;
+mod migration;
+mod pause;
+mod ttl;
+
+pub use error::Error;
+pub use pause::{PausableFunction, PauseScope};
+
+#[cfg(test)]
+mod test;
+
 
 #[derive(Clone)]
 #[contracttype]
@@ -24,12 +36,20 @@ pub struct Loan {
     pub paid_principal: i128,
     pub term_in_months: u32,
     pub start_date: u64,
+    /// USDC sent to the farmer when the loan was accepted.
+    pub disbursed: i128,
 }
 
 #[contracttype]
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum DataKey {
     Addresses,
+    Admin,
+    Version,
+    /// Fund contract that lends the capital and bears the losses.
+    Fund,
+    /// Capital drawn from the fund that farmers still owe back.
+    Deployed,
 }
 
 #[contracttype]
@@ -37,10 +57,17 @@ pub enum DataKey {
 pub enum LoanType {
     Request,
     Loan,
+    /// Principal of a written-off loan that hasn't been recovered yet.
+    WrittenOff,
+    /// Capital the fund lent for the farmer's open loan. Loans without it
+    /// were paid out of this contract's own USDC before it drew on the fund.
+    Drawn,
+    /// Written-off principal of such a loan, which this contract bore.
+    WrittenOffLocally,
 }
 
 
-#[derive(Clone)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 #[contracttype]
 pub struct ContractAddresses {
     pub soroswap_router: Address,
@@ -53,17 +80,44 @@ pub struct PaymentContract;
 
 #[contractimpl]
 impl PaymentContract {
-    pub fn init(env: Env, router: Address, comet: Address, usdc: Address) {
+    pub fn init(env: Env, admin: Address, router: Address, comet: Address, usdc: Address) -> Result<(), Error> {
+        if env.storage().persistent().has(&DataKey::Admin) {
+            return Err(Error::AlreadyInitialized);
+        }
+
         let addresses = ContractAddresses {
             soroswap_router: router,
             blend_comet: comet,
             usdc_token: usdc,
         };
+        env.storage().persistent().set(&DataKey::Admin, &admin);
+        ttl::extend_persistent(&env, &DataKey::Admin);
         env.storage().persistent().set(&DataKey::Addresses, &addresses);
+        ttl::extend_persistent(&env, &DataKey::Addresses);
+        migration::write_version(&env, migration::SCHEMA_VERSION);
+        ttl::extend_instance(&env);
+
+        events::publish(&env, "initialized", &admin, addresses);
+        Ok(())
     }
 
-    pub fn request_credit(env: Env, farmer: Address, amount: i128, interest_rate: i128, loan_fee: i128) {
+    pub fn request_credit(
+        env: Env,
+        farmer: Address,
+        amount: i128,
+        interest_rate: i128,
+        loan_fee: i128,
+    ) -> Result<(), Error> {
         farmer.require_auth();
+        pause::require_not_paused(&env, PausableFunction::RequestCredit)?;
+
+        if amount <= 0 || interest_rate < 0 || loan_fee < 0 {
+            return Err(Error::InvalidAmount);
+        }
+        // A new request would reopen the approval of the loan still running.
+        if has_loan(&env, &farmer) {
+            return Err(Error::LoanAlreadyExists);
+        }
 
         let req = LoanRequest {
             farmer: farmer.clone(),
@@ -73,18 +127,36 @@ impl PaymentContract {
             approved: false,
         };
         
-        env.storage().persistent().set(&(LoanType::Request, farmer), &req);
+        save_loan_request(&env, &req);
+
+        events::publish(
+            &env,
+            "credit_requested",
+            &farmer,
+            events::CreditRequested {
+                amount,
+                interest_rate,
+                loan_fee,
+            },
+        );
+        Ok(())
     }
 
-    pub fn accept_credit(env: Env, farmer: Address, term_in_months: u32) {
-        let mut req: LoanRequest = env.storage().persistent().get(&(LoanType::Request, farmer.clone())).unwrap();
+    /// Approves `farmer`'s credit request and pays it out of the fund's
+    /// senior tranche. Only the admin can approve a loan.
+    pub fn accept_credit(env: Env, admin: Address, farmer: Address, term_in_months: u32) -> Result<(), Error> {
+        require_admin(&env, &admin)?;
+        pause::require_not_paused(&env, PausableFunction::AcceptCredit)?;
+        let mut req = load_loan_request(&env, &farmer)?;
 
         if req.approved {
-            panic!("Crédito já aprovado");
+            return Err(Error::AlreadyApproved);
+        }
+        if has_loan(&env, &farmer) {
+            return Err(Error::LoanAlreadyExists);
         }
         
-        let discount = req.amount * 10 / 100;
-        let discounted_amount = req.amount - discount;
+        let discounted_amount = disbursed_amount(req.amount);
 
         let total_principal = discounted_amount + (discounted_amount * req.loan_fee / 100);
 
@@ -96,91 +168,600 @@ impl PaymentContract {
             paid_principal: 0,
             term_in_months,
             start_date: env.ledger().timestamp(),
+            disbursed: discounted_amount,
         };
 
-        env.storage().persistent().set(&(LoanType::Loan, farmer.clone()), &loan);
+        let fund = read_fund(&env)?;
 
-        req.approved = true;
-        env.storage().persistent().set(&(LoanType::Request, farmer.clone()), &req);
+        save_loan(&env, &loan);
+        let drawn_key = (LoanType::Drawn, farmer.clone());
+        env.storage().persistent().set(&drawn_key, &discounted_amount);
+        ttl::extend_persistent(&env, &drawn_key);
 
-        let addresses: ContractAddresses = env.storage().persistent().get(&DataKey::Addresses).unwrap();
+        req.approved = true;
+        save_loan_request(&env, &req);
+        add_deployed(&env, discounted_amount);
 
         env.invoke_contract::<()>(
-            &addresses.usdc_token,
-            &Symbol::new(&env, "transfer"),
+            &fund,
+            &Symbol::new(&env, "draw_capital"),
             vec![
                 &env,
                 env.current_contract_address().into_val(&env),
-                farmer.into_val(&env),
+                farmer.clone().into_val(&env),
                 discounted_amount.into_val(&env),
             ],
         );
+
+        events::publish(
+            &env,
+            "credit_accepted",
+            &farmer,
+            events::CreditAccepted {
+                principal: loan.principal,
+                disbursed: loan.disbursed,
+                interest_rate: loan.interest_rate,
+                term_in_months,
+            },
+        );
+        Ok(())
     }
 
-    pub fn pay_installment(env: Env, farmer: Address, amount: i128) {
+    pub fn pay_installment(env: Env, farmer: Address, amount: i128) -> Result<(), Error> {
         farmer.require_auth();
+        pause::require_not_paused(&env, PausableFunction::PayInstallment)?;
+
+        if amount <= 0 {
+            return Err(Error::InvalidAmount);
+        }
         
-        let mut loan: Loan = env.storage().persistent().get(&(LoanType::Loan, farmer.clone())).unwrap();
+        let loan = load_loan(&env, &farmer)?;
+        let (interest, subsidy) = take_payment(&env, &farmer, loan.clone(), amount)?;
+        let remaining_principal = (loan.principal - loan.paid_principal - amount).max(0);
+
+        events::publish(
+            &env,
+            "installment_paid",
+            &farmer,
+            events::InstallmentPaid {
+                amount,
+                interest,
+                subsidy,
+                remaining_principal,
+            },
+        );
+        Ok(())
+    }
 
-        loan.paid_principal += amount;
+    /// Pays off the rest of `farmer`'s loan as one last installment of the
+    /// principal still owed. Each installment already paid the interest on
+    /// what it left owing, so nothing else is due. Returns what the farmer
+    /// paid.
+    pub fn pay_off_loan(env: Env, farmer: Address) -> Result<i128, Error> {
+        farmer.require_auth();
+        pause::require_not_paused(&env, PausableFunction::PayOffLoan)?;
 
-        let addresses: ContractAddresses = env.storage().persistent().get(&DataKey::Addresses).unwrap();
-        
-        let remaining_principal = loan.principal - loan.paid_principal;
-        let installment_interest = remaining_principal * loan.interest_rate / 100;
-        
-        loan.total_interest_paid += installment_interest;
+        let loan = load_loan(&env, &farmer)?;
+        let outstanding = loan.principal - loan.paid_principal;
+        let (interest, subsidy) = take_payment(&env, &farmer, loan, outstanding)?;
+        let amount_due = outstanding + interest - subsidy;
 
-        if loan.paid_principal >= loan.principal {
-            env.storage().persistent().remove(&(LoanType::Loan, farmer.clone()));
-        } else {
-            env.storage().persistent().set(&(LoanType::Loan, farmer), &loan);
+        events::publish(&env, "loan_paid_off", &farmer, events::LoanPaidOff { amount: amount_due });
+
+        Ok(amount_due)
+    }
+    
+    /// Writes off a defaulted loan. The capital the farmer still owes the
+    /// fund is claimed from its first-loss guarantee, the rest is reported
+    /// to it as a loss, and all of it is kept on record here until it is
+    /// recovered. A loan this contract lent itself is written off here
+    /// only. Returns that capital.
+    pub fn write_off_loan(env: Env, admin: Address, farmer: Address) -> Result<i128, Error> {
+        require_admin(&env, &admin)?;
+
+        let loan = load_loan(&env, &farmer)?;
+        let loss = loan.disbursed - returned_capital(&loan);
+
+        if !is_fund_loan(&env, &farmer) {
+            close_loan(&env, &farmer);
+            let written_off = read_written_off(&env, LoanType::WrittenOffLocally, &farmer) + loss;
+            write_written_off(&env, LoanType::WrittenOffLocally, &farmer, written_off);
+
+            events::publish(&env, "loan_written_off", &farmer, events::LoanWrittenOff { loss, guaranteed: 0 });
+            return Ok(loss);
         }
 
-        env.invoke_contract::<()>(
-            &addresses.blend_comet,
-            &Symbol::new(&env, "supply"),
-            vec![
-                &env,
-                addresses.usdc_token.into_val(&env),
-                installment_interest.into_val(&env),
-            ],
+        let fund = read_fund(&env)?;
+        close_loan(&env, &farmer);
+        add_deployed(&env, -loss);
+        let written_off = read_written_off(&env, LoanType::WrittenOff, &farmer) + loss;
+        write_written_off(&env, LoanType::WrittenOff, &farmer, written_off);
+
+        let mut guaranteed = 0;
+        if loss > 0 {
+            guaranteed = env.invoke_contract::<i128>(
+                &fund,
+                &Symbol::new(&env, "claim_guarantee"),
+                vec![
+                    &env,
+                    env.current_contract_address().into_val(&env),
+                    farmer.clone().into_val(&env),
+                    loss.into_val(&env),
+                ],
+            );
+        }
+        if loss > guaranteed {
+            env.invoke_contract::<Val>(
+                &fund,
+                &Symbol::new(&env, "write_off"),
+                vec![
+                    &env,
+                    env.current_contract_address().into_val(&env),
+                    farmer.clone().into_val(&env),
+                    (loss - guaranteed).into_val(&env),
+                ],
+            );
+        }
+
+        events::publish(&env, "loan_written_off", &farmer, events::LoanWrittenOff { loss, guaranteed });
+        Ok(loss)
+    }
+
+    /// Pays `amount` towards a written-off loan. The USDC goes straight to
+    /// the fund, which hands it back to the tranches that took the loss.
+    /// What this contract lost on loans it lent itself is recovered after
+    /// the fund's losses, and stays here. Returns what is still unrecovered.
+    pub fn recover_loan(env: Env, payer: Address, farmer: Address, amount: i128) -> Result<i128, Error> {
+        payer.require_auth();
+
+        let fund_loss = read_written_off(&env, LoanType::WrittenOff, &farmer);
+        let local_loss = read_written_off(&env, LoanType::WrittenOffLocally, &farmer);
+        let written_off = fund_loss + local_loss;
+        if written_off == 0 {
+            return Err(Error::LoanNotFound);
+        }
+        if amount <= 0 || amount > written_off {
+            return Err(Error::InvalidAmount);
+        }
+
+        let addresses = load_addresses(&env)?;
+        let to_fund = amount.min(fund_loss);
+        let to_contract = amount - to_fund;
+
+        let outstanding = written_off - amount;
+        write_written_off(&env, LoanType::WrittenOff, &farmer, fund_loss - to_fund);
+        write_written_off(&env, LoanType::WrittenOffLocally, &farmer, local_loss - to_contract);
+
+        if to_fund > 0 {
+            let fund = read_fund(&env)?;
+            env.invoke_contract::<()>(
+                &addresses.usdc_token,
+                &Symbol::new(&env, "transfer"),
+                vec![
+                    &env,
+                    payer.clone().into_val(&env),
+                    fund.clone().into_val(&env),
+                    to_fund.into_val(&env),
+                ],
+            );
+            env.invoke_contract::<Val>(
+                &fund,
+                &Symbol::new(&env, "recover"),
+                vec![
+                    &env,
+                    env.current_contract_address().into_val(&env),
+                    farmer.clone().into_val(&env),
+                    to_fund.into_val(&env),
+                ],
+            );
+        }
+        if to_contract > 0 {
+            env.invoke_contract::<()>(
+                &addresses.usdc_token,
+                &Symbol::new(&env, "transfer"),
+                vec![
+                    &env,
+                    payer.clone().into_val(&env),
+                    env.current_contract_address().into_val(&env),
+                    to_contract.into_val(&env),
+                ],
+            );
+        }
+
+        events::publish(
+            &env,
+            "loan_recovered",
+            &farmer,
+            events::LoanRecovered {
+                payer,
+                amount,
+                outstanding,
+            },
         );
+        Ok(outstanding)
     }
 
-    pub fn pay_off_loan(env: Env, farmer: Address) -> i128 {
-        farmer.require_auth();
+    /// Written-off principal on `farmer`'s loans not yet recovered.
+    pub fn get_written_off(env: Env, farmer: Address) -> i128 {
+        read_written_off(&env, LoanType::WrittenOff, &farmer)
+            + read_written_off(&env, LoanType::WrittenOffLocally, &farmer)
+    }
+
+    /// Capital drawn from the fund for loans that are still open. It
+    /// matches the fund's `loans_receivable` for loans made through it.
+    pub fn get_deployed_capital(env: Env) -> i128 {
+        env.storage().instance().get(&DataKey::Deployed).unwrap_or(0)
+    }
 
-        let loan: Loan = env.storage().persistent().get(&(LoanType::Loan, farmer.clone())).unwrap();
+    /// Sets the fund that lends out the capital for new loans and gets
+    /// repayments, write-offs and recoveries.
+    pub fn set_fund(env: Env, admin: Address, fund: Address) -> Result<(), Error> {
+        require_admin(&env, &admin)?;
 
-        let addresses: ContractAddresses = env.storage().persistent().get(&DataKey::Addresses).unwrap();
+        env.storage().persistent().set(&DataKey::Fund, &fund);
+        ttl::extend_persistent(&env, &DataKey::Fund);
 
-        let time_passed = env.ledger().timestamp() - loan.start_date;
-        let months_passed = (time_passed / 2592000) as u32;
+        events::publish(&env, "fund_set", &admin, fund);
+        Ok(())
+    }
 
-        let amount_due = (loan.principal - loan.paid_principal) + (loan.total_interest_paid);
+    pub fn get_fund(env: Env) -> Result<Address, Error> {
+        read_fund(&env)
+    }
 
-        env.storage().persistent().remove(&(LoanType::Loan, farmer.clone()));
+    pub fn get_loan_request(env: Env, farmer: Address) -> Result<LoanRequest, Error> {
+        load_loan_request(&env, &farmer)
+    }
 
-        env.invoke_contract::<()>(
-            &addresses.usdc_token,
-            &Symbol::new(&env, "transfer"),
-            vec![
-                &env,
-                farmer.into_val(&env),
-                env.current_contract_address().into_val(&env),
-                amount_due.into_val(&env),
-            ],
+    pub fn get_loan(env: Env, farmer: Address) -> Result<Loan, Error> {
+        load_loan(&env, &farmer)
+    }
+
+    pub fn get_admin(env: Env) -> Result<Address, Error> {
+        read_admin(&env)
+    }
+
+    /// Lets `account` pause and unpause the contract, or takes that away.
+    pub fn set_pauser(env: Env, admin: Address, account: Address, granted: bool) -> Result<(), Error> {
+        require_admin(&env, &admin)?;
+
+        pause::set_pauser(&env, &account, granted);
+
+        events::publish(&env, "pauser_updated", &account, events::PauserUpdated { admin, granted });
+        Ok(())
+    }
+
+    /// The admin can always pause; other accounts need a grant.
+    pub fn is_pauser(env: Env, account: Address) -> Result<bool, Error> {
+        Ok(account == read_admin(&env)? || pause::is_pauser(&env, &account))
+    }
+
+    /// Stops or resumes every pausable entrypoint at once.
+    pub fn set_paused(env: Env, pauser: Address, paused: bool) -> Result<(), Error> {
+        require_pauser(&env, &pauser)?;
+
+        let scope = PauseScope::All;
+        pause::set_paused(&env, &scope, paused);
+
+        events::publish(&env, "pause_updated", &pauser, events::PauseUpdated { scope, paused });
+        Ok(())
+    }
+
+    /// Stops or resumes a single entrypoint. The global flag overrides it.
+    pub fn set_function_paused(
+        env: Env,
+        pauser: Address,
+        function: PausableFunction,
+        paused: bool,
+    ) -> Result<(), Error> {
+        require_pauser(&env, &pauser)?;
+
+        let scope = PauseScope::Function(function);
+        pause::set_paused(&env, &scope, paused);
+
+        events::publish(&env, "pause_updated", &pauser, events::PauseUpdated { scope, paused });
+        Ok(())
+    }
+
+    pub fn is_paused(env: Env) -> bool {
+        pause::is_paused(&env)
+    }
+
+    pub fn is_function_paused(env: Env, function: PausableFunction) -> bool {
+        pause::is_function_paused(&env, function)
+    }
+
+    /// Bumps the instance and the contract-wide persistent entries.
+    pub fn bump_all(env: Env, admin: Address) -> Result<(), Error> {
+        require_admin(&env, &admin)?;
+
+        ttl::extend_instance(&env);
+        ttl::extend_persistent_if_present(&env, &DataKey::Admin);
+        ttl::extend_persistent_if_present(&env, &DataKey::Addresses);
+        ttl::extend_persistent_if_present(&env, &DataKey::Version);
+        ttl::extend_persistent_if_present(&env, &DataKey::Fund);
+        Ok(())
+    }
+
+    /// Bumps a farmer's loan request and loan, for loans that sit idle
+    /// between installments longer than their TTL.
+    pub fn extend_loan_ttl(env: Env, admin: Address, farmer: Address) -> Result<(), Error> {
+        require_admin(&env, &admin)?;
+
+        let request = ttl::extend_persistent_if_present(&env, &(LoanType::Request, farmer.clone()));
+        let loan = ttl::extend_persistent_if_present(&env, &(LoanType::Loan, farmer.clone()));
+        ttl::extend_persistent_if_present(&env, &(LoanType::Drawn, farmer.clone()));
+        let written_off = ttl::extend_persistent_if_present(&env, &(LoanType::WrittenOff, farmer.clone()))
+            | ttl::extend_persistent_if_present(&env, &(LoanType::WrittenOffLocally, farmer));
+        if !request && !loan && !written_off {
+            return Err(Error::LoanNotFound);
+        }
+        Ok(())
+    }
+
+    /// Replaces the contract's code. Storage is kept as is; run `migrate`
+    /// afterwards if the new code expects a newer layout.
+    pub fn upgrade(env: Env, admin: Address, new_wasm_hash: BytesN<32>) -> Result<(), Error> {
+        require_admin(&env, &admin)?;
+
+        events::publish(&env, "upgraded", &admin, new_wasm_hash.clone());
+        env.deployer().update_current_contract_wasm(new_wasm_hash);
+        Ok(())
+    }
+
+    /// Moves storage to the layout this code expects.
+    ///
+    /// Old loans are also rewritten the first time they are read, so this
+    /// only needs the loans worth converting up front. Returns how many of
+    /// `farmers` had a loan.
+    pub fn migrate(env: Env, admin: Address, farmers: Vec<Address>) -> Result<u32, Error> {
+        require_admin(&env, &admin)?;
+
+        let mut migrated = 0;
+        for farmer in farmers.iter() {
+            if migration::read_loan(&env, &farmer).is_some() {
+                migrated += 1;
+            }
+        }
+        migration::write_version(&env, migration::SCHEMA_VERSION);
+
+        events::publish(
+            &env,
+            "migrated",
+            &admin,
+            events::Migrated {
+                version: migration::SCHEMA_VERSION,
+                migrated,
+            },
         );
+        Ok(migrated)
+    }
 
-        amount_due
+    /// Storage layout version currently in place.
+    pub fn get_version(env: Env) -> u32 {
+        migration::read_version(&env)
     }
-    
-    pub fn get_loan_request(env: Env, farmer: Address) -> LoanRequest {
-        env.storage().persistent().get(&(LoanType::Request, farmer)).unwrap()
+}
+
+/// Repayments go to the fund's capital first; the rest of the principal,
+/// which is the loan fee, is income like interest.
+fn returned_capital(loan: &Loan) -> i128 {
+    loan.paid_principal.min(loan.disbursed)
+}
+
+fn add_deployed(env: &Env, amount: i128) {
+    let deployed: i128 = env.storage().instance().get(&DataKey::Deployed).unwrap_or(0);
+    env.storage().instance().set(&DataKey::Deployed, &(deployed + amount));
+}
+
+/// Takes `amount` of principal on `farmer`'s `loan` from the farmer with
+/// the interest due on what is left after it, and closes the loan once the
+/// principal is repaid. The fund's donation pool may cover part of the
+/// interest on a loan it lent. Returns the interest and that subsidy.
+fn take_payment(env: &Env, farmer: &Address, mut loan: Loan, amount: i128) -> Result<(i128, i128), Error> {
+    let fund_loan = is_fund_loan(env, farmer);
+
+    let returned = returned_capital(&loan);
+    loan.paid_principal += amount;
+    let capital = returned_capital(&loan) - returned;
+
+    let remaining_principal = loan.principal - loan.paid_principal;
+    let interest = remaining_principal.max(0) * loan.interest_rate / 100;
+
+    loan.total_interest_paid += interest;
+
+    if loan.paid_principal >= loan.principal {
+        close_loan(env, farmer);
+    } else {
+        save_loan(env, &loan);
     }
 
-    pub fn get_loan(env: Env, farmer: Address) -> Loan {
-        env.storage().persistent().get(&(LoanType::Loan, farmer)).unwrap()
+    let mut subsidy = 0;
+    if fund_loan {
+        let fund = read_fund(env)?;
+        if interest > 0 {
+            subsidy = env.invoke_contract::<i128>(
+                &fund,
+                &Symbol::new(env, "subsidize_interest"),
+                vec![
+                    env,
+                    env.current_contract_address().into_val(env),
+                    farmer.clone().into_val(env),
+                    loan.start_date.into_val(env),
+                    interest.into_val(env),
+                ],
+            );
+        }
+        repay_fund(env, &fund, farmer, capital, amount + interest - subsidy - capital)?;
+    } else {
+        collect(env, farmer, amount + interest)?;
+    }
+    Ok((interest, subsidy))
+}
+
+/// Sends a farmer's payment of `capital` and `income` to the fund and has
+/// it booked there.
+fn repay_fund(env: &Env, fund: &Address, farmer: &Address, capital: i128, income: i128) -> Result<(), Error> {
+    if capital + income == 0 {
+        return Ok(());
     }
+    let addresses = load_addresses(env)?;
+    add_deployed(env, -capital);
+
+    env.invoke_contract::<()>(
+        &addresses.usdc_token,
+        &Symbol::new(env, "transfer"),
+        vec![
+            env,
+            farmer.into_val(env),
+            fund.into_val(env),
+            (capital + income).into_val(env),
+        ],
+    );
+    env.invoke_contract::<()>(
+        fund,
+        &Symbol::new(env, "repay_capital"),
+        vec![
+            env,
+            env.current_contract_address().into_val(env),
+            farmer.into_val(env),
+            capital.into_val(env),
+            income.into_val(env),
+        ],
+    );
+    Ok(())
+}
+
+/// Whether `farmer`'s open loan was lent by the fund. Loans accepted
+/// before the contract drew on the fund were paid out of its own USDC, so
+/// their repayments and losses are settled here and the fund never hears
+/// of them.
+/// A farmer has one loan at a time; a new one waits until it is paid off or
+/// written off.
+fn has_loan(env: &Env, farmer: &Address) -> bool {
+    env.storage().persistent().has(&(LoanType::Loan, farmer.clone()))
+}
+
+fn is_fund_loan(env: &Env, farmer: &Address) -> bool {
+    ttl::extend_persistent_if_present(env, &(LoanType::Drawn, farmer.clone()))
+}
+
+fn close_loan(env: &Env, farmer: &Address) {
+    env.storage().persistent().remove(&(LoanType::Loan, farmer.clone()));
+    env.storage().persistent().remove(&(LoanType::Drawn, farmer.clone()));
+}
+
+/// Takes a farmer's payment on a loan this contract lent itself.
+fn collect(env: &Env, farmer: &Address, amount: i128) -> Result<(), Error> {
+    if amount == 0 {
+        return Ok(());
+    }
+    let addresses = load_addresses(env)?;
+
+    env.invoke_contract::<()>(
+        &addresses.usdc_token,
+        &Symbol::new(env, "transfer"),
+        vec![
+            env,
+            farmer.into_val(env),
+            env.current_contract_address().into_val(env),
+            amount.into_val(env),
+        ],
+    );
+    Ok(())
+}
+
+/// The farmer receives the requested amount less a 10% discount.
+pub(crate) fn disbursed_amount(amount: i128) -> i128 {
+    amount - amount * 10 / 100
+}
+
+fn read_admin(env: &Env) -> Result<Address, Error> {
+    ttl::extend_instance(env);
+    let admin = env.storage().persistent().get(&DataKey::Admin).ok_or(Error::NotInitialized)?;
+    ttl::extend_persistent(env, &DataKey::Admin);
+    Ok(admin)
+}
+
+fn require_admin(env: &Env, caller: &Address) -> Result<(), Error> {
+    caller.require_auth();
+    if *caller != read_admin(env)? {
+        return Err(Error::Unauthorized);
+    }
+    Ok(())
+}
+
+fn require_pauser(env: &Env, caller: &Address) -> Result<(), Error> {
+    caller.require_auth();
+    if *caller != read_admin(env)? && !pause::is_pauser(env, caller) {
+        return Err(Error::Unauthorized);
+    }
+    Ok(())
+}
+
+fn load_addresses(env: &Env) -> Result<ContractAddresses, Error> {
+    ttl::extend_instance(env);
+    let addresses = env.storage().persistent().get(&DataKey::Addresses).ok_or(Error::NotInitialized)?;
+    ttl::extend_persistent(env, &DataKey::Addresses);
+    Ok(addresses)
+}
+
+fn read_fund(env: &Env) -> Result<Address, Error> {
+    let fund = env.storage().persistent().get(&DataKey::Fund).ok_or(Error::FundNotSet)?;
+    ttl::extend_persistent(env, &DataKey::Fund);
+    Ok(fund)
+}
+
+/// Written-off principal on `farmer`'s loans that hasn't been recovered:
+/// the fund's with `WrittenOff`, this contract's with `WrittenOffLocally`.
+fn read_written_off(env: &Env, kind: LoanType, farmer: &Address) -> i128 {
+    let key = (kind, farmer.clone());
+    match env.storage().persistent().get(&key) {
+        Some(amount) => {
+            ttl::extend_persistent(env, &key);
+            amount
+        }
+        None => 0,
+    }
+}
+
+fn write_written_off(env: &Env, kind: LoanType, farmer: &Address, amount: i128) {
+    let key = (kind, farmer.clone());
+    if amount == 0 {
+        env.storage().persistent().remove(&key);
+    } else {
+        env.storage().persistent().set(&key, &amount);
+        ttl::extend_persistent(env, &key);
+    }
+}
+
+fn load_loan_request(env: &Env, farmer: &Address) -> Result<LoanRequest, Error> {
+    ttl::extend_instance(env);
+    let key = (LoanType::Request, farmer.clone());
+    let req = env.storage().persistent().get(&key).ok_or(Error::LoanRequestNotFound)?;
+    ttl::extend_persistent(env, &key);
+    Ok(req)
+}
+
+fn save_loan_request(env: &Env, req: &LoanRequest) {
+    ttl::extend_instance(env);
+    let key = (LoanType::Request, req.farmer.clone());
+    env.storage().persistent().set(&key, req);
+    ttl::extend_persistent(env, &key);
+}
+
+fn load_loan(env: &Env, farmer: &Address) -> Result<Loan, Error> {
+    ttl::extend_instance(env);
+    let key = (LoanType::Loan, farmer.clone());
+    let loan = migration::read_loan(env, farmer).ok_or(Error::LoanNotFound)?;
+    ttl::extend_persistent(env, &key);
+    // The approved request is the loan's record, so it lives as long as the loan.
+    ttl::extend_persistent_if_present(env, &(LoanType::Request, farmer.clone()));
+    Ok(loan)
+}
+
+fn save_loan(env: &Env, loan: &Loan) {
+    let key = (LoanType::Loan, loan.farmer.clone());
+    env.storage().persistent().set(&key, loan);
+    ttl::extend_persistent(env, &key);
 }
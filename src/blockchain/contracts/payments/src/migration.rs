@@ -0,0 +1,63 @@
+use soroban_sdk::{contracttype, Address, Env, Map, Symbol, TryFromVal, Val};
+
+use crate::{disbursed_amount, ttl, DataKey, Loan, LoanRequest, LoanType};
+
+/// Storage layout this code reads and writes.
+///
+/// 1. `Loan` without `disbursed`.
+/// 2. `Loan` records the USDC actually sent to the farmer.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// `Loan` as stored by schema version 1.
+#[derive(Clone)]
+#[contracttype]
+pub struct LoanV1 {
+    pub farmer: Address,
+    pub principal: i128,
+    pub interest_rate: i128,
+    pub total_interest_paid: i128,
+    pub paid_principal: i128,
+    pub term_in_months: u32,
+    pub start_date: u64,
+}
+
+/// Deployments from before the version was stored are on version 1.
+pub fn read_version(env: &Env) -> u32 {
+    let version = env.storage().persistent().get(&DataKey::Version).unwrap_or(1);
+    ttl::extend_persistent_if_present(env, &DataKey::Version);
+    version
+}
+
+pub fn write_version(env: &Env, version: u32) {
+    env.storage().persistent().set(&DataKey::Version, &version);
+    ttl::extend_persistent(env, &DataKey::Version);
+}
+
+/// Reads `farmer`'s loan in whatever layout it was stored, rewriting
+/// version 1 loans into the current layout on the way.
+pub fn read_loan(env: &Env, farmer: &Address) -> Option<Loan> {
+    let key = (LoanType::Loan, farmer.clone());
+    // Decoding a struct from a map with the wrong fields traps in the host,
+    // so tell the layouts apart by the field that version 2 added.
+    let raw: Map<Symbol, Val> = env.storage().persistent().get(&key)?;
+    if raw.contains_key(Symbol::new(env, "disbursed")) {
+        return Some(Loan::try_from_val(env, &raw.to_val()).expect("unknown loan layout"));
+    }
+
+    let old = LoanV1::try_from_val(env, &raw.to_val()).expect("unknown loan layout");
+    // Every accepted loan still has its request, which holds the amount the
+    // disbursement was computed from.
+    let request: Option<LoanRequest> = env.storage().persistent().get(&(LoanType::Request, farmer.clone()));
+    let loan = Loan {
+        farmer: old.farmer,
+        principal: old.principal,
+        interest_rate: old.interest_rate,
+        total_interest_paid: old.total_interest_paid,
+        paid_principal: old.paid_principal,
+        term_in_months: old.term_in_months,
+        start_date: old.start_date,
+        disbursed: request.map(|req| disbursed_amount(req.amount)).unwrap_or(old.principal),
+    };
+    env.storage().persistent().set(&key, &loan);
+    Some(loan)
+}
@@ -0,0 +1,77 @@
+use soroban_sdk::{contracttype, Address, Env};
+
+use crate::{ttl, Error};
+
+/// Entrypoints that can be paused on their own.
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PausableFunction {
+    RequestCredit,
+    AcceptCredit,
+    PayInstallment,
+    PayOffLoan,
+}
+
+/// What a pause flag covers. Flags are stored under their scope in
+/// instance storage, where an unset flag means not paused.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum PauseScope {
+    All,
+    Function(PausableFunction),
+}
+
+/// Pauser grants are persistent entries like the admin.
+#[derive(Clone)]
+#[contracttype]
+pub enum PauseKey {
+    Pauser(Address),
+}
+
+pub fn is_pauser(env: &Env, account: &Address) -> bool {
+    let key = PauseKey::Pauser(account.clone());
+    let granted = env.storage().persistent().get(&key).unwrap_or(false);
+    if granted {
+        ttl::extend_persistent(env, &key);
+    }
+    granted
+}
+
+pub fn set_pauser(env: &Env, account: &Address, granted: bool) {
+    let key = PauseKey::Pauser(account.clone());
+    if granted {
+        env.storage().persistent().set(&key, &true);
+        ttl::extend_persistent(env, &key);
+    } else {
+        env.storage().persistent().remove(&key);
+    }
+}
+
+pub fn is_paused(env: &Env) -> bool {
+    env.storage().instance().get(&PauseScope::All).unwrap_or(false)
+}
+
+/// Whether `function` is stopped, either on its own or by the global flag.
+pub fn is_function_paused(env: &Env, function: PausableFunction) -> bool {
+    is_paused(env)
+        || env
+            .storage()
+            .instance()
+            .get(&PauseScope::Function(function))
+            .unwrap_or(false)
+}
+
+pub fn set_paused(env: &Env, scope: &PauseScope, paused: bool) {
+    if paused {
+        env.storage().instance().set(scope, &true);
+    } else {
+        env.storage().instance().remove(scope);
+    }
+}
+
+pub fn require_not_paused(env: &Env, function: PausableFunction) -> Result<(), Error> {
+    if is_function_paused(env, function) {
+        return Err(Error::Paused);
+    }
+    Ok(())
+}
@@ -0,0 +1,1004 @@
+use soroban_sdk::{
+    contract, contractimpl,
+    testutils::{storage::Persistent as _, Address as AddressTestUtils, Events, Ledger, MockAuth, MockAuthInvoke},
+    token::{StellarAssetClient, TokenClient},
+    vec, Address, Env, IntoVal, Symbol, Val, Vec,
+};
+
+use fund::{Allocation, FundContract, FundContractClient, SwapParams};
+
+use crate::{
+    events::{self, CreditAccepted, CreditRequested, InstallmentPaid, LoanPaidOff, LoanRecovered, LoanWrittenOff, PauseUpdated},
+    migration::{LoanV1, SCHEMA_VERSION},
+    ttl::{DAY_IN_LEDGERS, PERSISTENT_BUMP_AMOUNT},
+    DataKey, Error, PausableFunction, PauseScope, PaymentContract, PaymentContractClient, Loan, LoanRequest, LoanType,
+    ContractAddresses,
+};
+
+#[contract]
+pub struct DummyRouter;
+
+#[contractimpl]
+impl DummyRouter {
+    pub fn swap_exact_tokens_for_tokens(
+        _env: Env,
+        _token_in: Address,
+        _amount_in: i128,
+        _token_out: Address,
+        _to: Address,
+    ) {}
+}
+
+#[contract]
+pub struct DummyToken;
+
+#[contractimpl]
+impl DummyToken {
+    pub fn transfer(_env: Env, _from: Address, _to: Address, _amount: i128) {}
+    pub fn approve(_env: Env, _from: Address, _spender: Address, _amount: i128, _expiration_ledger: u32) {}
+}
+
+#[contract]
+pub struct DummyComet;
+
+#[contractimpl]
+impl DummyComet {
+    pub fn supply(_env: Env, _token: Address, _amount: i128) {}
+    pub fn withdraw(_env: Env, _token: Address, _amount: i128) {}
+}
+
+/// Stand-in for the fund: remembers the last call the payment contract made
+/// and the amounts in it.
+#[contract]
+pub struct DummyFund;
+
+#[contractimpl]
+impl DummyFund {
+    pub fn draw_capital(env: Env, payment: Address, farmer: Address, amount: i128) {
+        Self::record(&env, payment, "draw_capital", farmer, vec![&env, amount]);
+    }
+
+    pub fn repay_capital(env: Env, payment: Address, farmer: Address, principal: i128, interest: i128) {
+        Self::record(&env, payment, "repay_capital", farmer, vec![&env, principal, interest]);
+    }
+
+    /// Covers `subsidy_bps` of the interest, as set with `set_subsidy_bps`.
+    pub fn subsidize_interest(env: Env, payment: Address, farmer: Address, _loan_start: u64, interest: i128) -> i128 {
+        let bps: i128 = env.storage().instance().get(&Symbol::new(&env, "bps")).unwrap_or(0);
+        let subsidy = interest * bps / 10_000;
+        Self::record(&env, payment, "subsidize_interest", farmer, vec![&env, interest, subsidy]);
+        subsidy
+    }
+
+    pub fn set_subsidy_bps(env: Env, bps: i128) {
+        env.storage().instance().set(&Symbol::new(&env, "bps"), &bps);
+    }
+
+    /// Covers up to the amount set with `set_guarantee`.
+    pub fn claim_guarantee(env: Env, payment: Address, farmer: Address, loss: i128) -> i128 {
+        let cover: i128 = env.storage().instance().get(&Symbol::new(&env, "cover")).unwrap_or(0);
+        let covered = loss.min(cover);
+        Self::record(&env, payment, "claim_guarantee", farmer, vec![&env, loss, covered]);
+        covered
+    }
+
+    pub fn set_guarantee(env: Env, cover: i128) {
+        env.storage().instance().set(&Symbol::new(&env, "cover"), &cover);
+    }
+
+    pub fn write_off(env: Env, payment: Address, farmer: Address, amount: i128) {
+        Self::record(&env, payment, "write_off", farmer, vec![&env, amount]);
+    }
+
+    pub fn recover(env: Env, payment: Address, farmer: Address, amount: i128) {
+        Self::record(&env, payment, "recover", farmer, vec![&env, amount]);
+    }
+
+    pub fn last(env: Env) -> (Symbol, Address, Vec<i128>) {
+        env.storage().instance().get(&Symbol::new(&env, "last")).unwrap()
+    }
+}
+
+impl DummyFund {
+    fn record(env: &Env, payment: Address, call: &str, farmer: Address, amounts: Vec<i128>) {
+        payment.require_auth();
+        env.storage()
+            .instance()
+            .set(&Symbol::new(env, "last"), &(Symbol::new(env, call), farmer, amounts));
+    }
+}
+
+pub fn setup() -> (Env, PaymentContractClient<'static>) {
+    let env = Env::default();
+    env.mock_all_auths(); 
+
+    let router_id = env.register(DummyRouter, ());
+    let comet_id = env.register(DummyComet, ());
+    let usdc_id = env.register(DummyToken, ());
+    let payment_contract_id = env.register(PaymentContract, ());
+
+    let client = PaymentContractClient::new(&env, &payment_contract_id);
+    client.init(&Address::generate(&env), &router_id, &comet_id, &usdc_id);
+
+    env.as_contract(&usdc_id, || {
+        let addresses = ContractAddresses {
+            soroswap_router: router_id,
+            blend_comet: comet_id,
+            usdc_token: usdc_id.clone(),
+        };
+        env.storage().persistent().set(&DataKey::Addresses, &addresses);
+    });
+    client.set_fund(&client.get_admin(), &env.register(DummyFund, ()));
+
+    (env, client)
+}
+
+fn fund(client: &PaymentContractClient) -> DummyFundClient<'static> {
+    DummyFundClient::new(&client.env, &client.get_fund())
+}
+
+/// The most recent contract event, in the shape `Events::all` returns.
+fn last_event(env: &Env) -> Vec<(Address, Vec<Val>, Val)> {
+    let events = env.events().all();
+    events.slice(events.len() - 1..)
+}
+
+/// A payment contract event as `Events::all` reports it.
+fn payment_event<D: IntoVal<Env, Val>>(
+    client: &PaymentContractClient,
+    action: &str,
+    subject: &Address,
+    payload: D,
+) -> (Address, Vec<Val>, Val) {
+    let env = &client.env;
+    let payload: Val = payload.into_val(env);
+    (
+        client.address.clone(),
+        (events::CONTRACT, Symbol::new(env, action), subject.clone()).into_val(env),
+        (events::version(action), payload).into_val(env),
+    )
+}
+
+#[test]
+fn test_accept_credit_with_discount() {
+    let (env, client) = setup();
+    let farmer = Address::generate(&env);
+    let amount = 10000;
+    let interest_rate = 5;
+    let loan_fee = 2;
+    let term_in_months = 12;
+
+    client.request_credit(&farmer, &amount, &interest_rate, &loan_fee);
+
+    client.accept_credit(&client.get_admin(), &farmer, &term_in_months);
+
+    let loan: Loan = client.get_loan(&farmer);
+    let loan_request: LoanRequest = client.get_loan_request(&farmer);
+
+    let expected_discount = amount * 10 / 100;
+    let expected_discounted_amount = amount - expected_discount;
+    let expected_total_principal = expected_discounted_amount + (expected_discounted_amount * loan_fee / 100);
+
+    assert_eq!(loan.principal, expected_total_principal);
+    assert!(loan_request.approved);
+    assert_eq!(loan.term_in_months, term_in_months);
+}
+
+#[test]
+fn test_pay_installment() {
+    let (env, client) = setup();
+    let farmer = Address::generate(&env);
+    let amount = 10000;
+    let interest_rate = 5;
+    let loan_fee = 2;
+    let term_in_months = 12;
+
+    client.request_credit(&farmer, &amount, &interest_rate, &loan_fee);
+
+    client.accept_credit(&client.get_admin(), &farmer, &term_in_months);
+
+    let payment_amount = 1000;
+    
+    client.pay_installment(&farmer, &payment_amount);
+
+    let loan: Loan = client.get_loan(&farmer); 
+    assert_eq!(loan.paid_principal, payment_amount);
+}
+
+
+#[test]
+fn test_pay_off_loan_successfully() {
+    let (env, client) = setup();
+    let farmer = Address::generate(&env);
+    let amount = 10000;
+    let interest_rate = 5;
+    let loan_fee = 2;
+    let term_in_months = 12;
+
+    client.request_credit(&farmer, &amount, &interest_rate, &loan_fee);
+    client.accept_credit(&client.get_admin(), &farmer, &term_in_months);
+
+    let paid_amount = client.pay_off_loan(&farmer);
+
+    assert_eq!(paid_amount, 9180);
+
+    let result = client.try_get_loan(&farmer);
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_pay_off_non_existent_loan() {
+    let (env, client) = setup();
+    let farmer = Address::generate(&env);
+    
+    let result = client.try_pay_off_loan(&farmer);
+    assert_eq!(result, Err(Ok(Error::LoanNotFound)));
+}
+
+#[test]
+fn test_accept_credit_twice() {
+    let (env, client) = setup();
+    let farmer = Address::generate(&env);
+
+    client.request_credit(&farmer, &10000, &5, &2);
+    client.accept_credit(&client.get_admin(), &farmer, &12);
+
+    let result = client.try_accept_credit(&client.get_admin(), &farmer, &12);
+    assert_eq!(result, Err(Ok(Error::AlreadyApproved)));
+}
+
+#[test]
+fn test_one_loan_at_a_time() {
+    let (env, client) = setup();
+    let farmer = Address::generate(&env);
+    let admin = client.get_admin();
+
+    client.request_credit(&farmer, &10000, &5, &2);
+    client.accept_credit(&admin, &farmer, &12);
+    assert_eq!(client.try_request_credit(&farmer, &5000, &5, &2), Err(Ok(Error::LoanAlreadyExists)));
+    assert!(client.get_loan_request(&farmer).approved);
+
+    // A request left unapproved next to a running loan can't be accepted either.
+    env.as_contract(&client.address, || {
+        let mut req: LoanRequest = env.storage().persistent().get(&(LoanType::Request, farmer.clone())).unwrap();
+        req.approved = false;
+        env.storage().persistent().set(&(LoanType::Request, farmer.clone()), &req);
+    });
+    assert_eq!(client.try_accept_credit(&admin, &farmer, &12), Err(Ok(Error::LoanAlreadyExists)));
+    assert_eq!(client.get_loan(&farmer).principal, 9180);
+    assert_eq!(client.get_deployed_capital(), 9000);
+
+    // Once it is paid off the farmer can borrow again.
+    client.pay_off_loan(&farmer);
+    client.request_credit(&farmer, &5000, &5, &2);
+    client.accept_credit(&admin, &farmer, &12);
+    assert_eq!(client.get_loan(&farmer).disbursed, 4500);
+}
+
+#[test]
+fn test_farmer_cannot_approve_their_own_loan() {
+    let (env, client) = setup();
+    let farmer = Address::generate(&env);
+    let admin = client.get_admin();
+    client.request_credit(&farmer, &10000, &0, &0);
+
+    // From here on only the farmer signs.
+    for approver in [farmer.clone(), admin.clone()] {
+        env.mock_auths(&[MockAuth {
+            address: &farmer,
+            invoke: &MockAuthInvoke {
+                contract: &client.address,
+                fn_name: "accept_credit",
+                args: (approver.clone(), farmer.clone(), 12_u32).into_val(&env),
+                sub_invokes: &[],
+            },
+        }]);
+        let result = client.try_accept_credit(&approver, &farmer, &12);
+        if approver == farmer {
+            assert_eq!(result, Err(Ok(Error::Unauthorized)));
+        } else {
+            // Naming the admin takes the admin's signature.
+            assert!(result.is_err());
+        }
+    }
+
+    assert!(!client.get_loan_request(&farmer).approved);
+    assert_eq!(client.get_deployed_capital(), 0);
+}
+
+#[test]
+fn test_accept_credit_without_request() {
+    let (env, client) = setup();
+    let farmer = Address::generate(&env);
+
+    let result = client.try_accept_credit(&client.get_admin(), &farmer, &12);
+    assert_eq!(result, Err(Ok(Error::LoanRequestNotFound)));
+}
+
+#[test]
+fn test_request_credit_invalid_amount() {
+    let (env, client) = setup();
+    let farmer = Address::generate(&env);
+
+    let result = client.try_request_credit(&farmer, &0, &5, &2);
+    assert_eq!(result, Err(Ok(Error::InvalidAmount)));
+}
+
+#[test]
+fn test_pay_installment_without_loan() {
+    let (env, client) = setup();
+    let farmer = Address::generate(&env);
+
+    let result = client.try_pay_installment(&farmer, &1000);
+    assert_eq!(result, Err(Ok(Error::LoanNotFound)));
+}
+/// Moves the ledger forward, keeping the token, Blend and fund stand-ins
+/// alive as their own maintainers would on the real network.
+fn advance_days(env: &Env, client: &PaymentContractClient, days: u32) {
+    let addresses: ContractAddresses = env.as_contract(&client.address, || {
+        env.storage().persistent().get(&DataKey::Addresses).unwrap()
+    });
+    let live_for = (days + 1) * DAY_IN_LEDGERS;
+    let fund = client.get_fund();
+    for id in [addresses.soroswap_router, addresses.blend_comet, addresses.usdc_token, fund] {
+        env.as_contract(&id, || env.storage().instance().extend_ttl(live_for, live_for));
+    }
+    env.ledger().with_mut(|li| li.sequence_number += days * DAY_IN_LEDGERS);
+}
+
+#[test]
+fn test_init_only_once() {
+    let (env, client) = setup();
+
+    let result = client.try_init(
+        &Address::generate(&env),
+        &Address::generate(&env),
+        &Address::generate(&env),
+        &Address::generate(&env),
+    );
+    assert_eq!(result, Err(Ok(Error::AlreadyInitialized)));
+}
+
+#[test]
+fn test_monthly_installments_keep_loan_alive() {
+    let (env, client) = setup();
+    let farmer = Address::generate(&env);
+
+    client.request_credit(&farmer, &10000, &5, &2);
+    client.accept_credit(&client.get_admin(), &farmer, &12);
+
+    let loan_ttl = || {
+        env.as_contract(&client.address, || {
+            env.storage().persistent().get_ttl(&(LoanType::Loan, farmer.clone()))
+        })
+    };
+    assert_eq!(loan_ttl(), PERSISTENT_BUMP_AMOUNT);
+
+    // A year of payments a month apart, each well past the instance TTL.
+    for _ in 0..11 {
+        advance_days(&env, &client, 30);
+        client.pay_installment(&farmer, &100);
+        assert_eq!(loan_ttl(), PERSISTENT_BUMP_AMOUNT);
+    }
+
+    assert_eq!(client.get_loan(&farmer).paid_principal, 1100);
+    assert!(client.get_loan_request(&farmer).approved);
+}
+
+#[test]
+fn test_extend_loan_ttl_keeps_idle_loan_alive() {
+    let (env, client) = setup();
+    let admin = client.get_admin();
+    let farmer = Address::generate(&env);
+
+    client.request_credit(&farmer, &10000, &5, &2);
+    client.accept_credit(&client.get_admin(), &farmer, &12);
+
+    // Half a year without a single payment.
+    for _ in 0..30 {
+        env.ledger().with_mut(|li| li.sequence_number += 6 * DAY_IN_LEDGERS);
+        client.bump_all(&admin);
+        client.extend_loan_ttl(&admin, &farmer);
+    }
+
+    assert_eq!(client.get_loan(&farmer).principal, 9180);
+    assert!(client.get_loan_request(&farmer).approved);
+}
+
+#[test]
+fn test_extend_loan_ttl_without_loan() {
+    let (env, client) = setup();
+    let farmer = Address::generate(&env);
+
+    let result = client.try_extend_loan_ttl(&client.get_admin(), &farmer);
+    assert_eq!(result, Err(Ok(Error::LoanNotFound)));
+}
+
+#[test]
+fn test_maintenance_requires_admin() {
+    let (env, client) = setup();
+    let farmer = Address::generate(&env);
+    client.request_credit(&farmer, &10000, &5, &2);
+
+    let attacker = Address::generate(&env);
+    assert_eq!(client.try_bump_all(&attacker), Err(Ok(Error::Unauthorized)));
+    assert_eq!(client.try_extend_loan_ttl(&attacker, &farmer), Err(Ok(Error::Unauthorized)));
+}
+
+/// Release build of this code, see `make testdata`.
+mod payment_wasm {
+    soroban_sdk::contractimport!(file = "testdata/payment.wasm");
+}
+
+#[test]
+fn test_upgrade_keeps_loans() {
+    let (env, client) = setup();
+    let admin = client.get_admin();
+
+    let farmer = Address::generate(&env);
+    let other_farmer = Address::generate(&env);
+    client.request_credit(&farmer, &10000, &5, &2);
+    client.accept_credit(&admin, &farmer, &12);
+    client.pay_installment(&farmer, &1000);
+    client.request_credit(&other_farmer, &5000, &4, &1);
+    let loan_before = client.get_loan(&farmer);
+    let fund_before = client.get_fund();
+
+    // Uploading a release build costs more than the default test budget.
+    env.cost_estimate().budget().reset_unlimited();
+    let new_wasm_hash = env.deployer().upload_contract_wasm(payment_wasm::WASM);
+    client.upgrade(&admin, &new_wasm_hash);
+
+    // From here on the calls run the uploaded build.
+    let upgraded = payment_wasm::Client::new(&env, &client.address);
+    assert_eq!(upgraded.migrate(&admin, &vec![&env, farmer.clone()]), 1);
+    assert_eq!(upgraded.get_version(), SCHEMA_VERSION);
+    assert_eq!(upgraded.get_admin(), admin);
+    assert_eq!(upgraded.get_fund(), fund_before);
+
+    let loan = upgraded.get_loan(&farmer);
+    assert_eq!(loan.principal, loan_before.principal);
+    assert_eq!(loan.paid_principal, loan_before.paid_principal);
+    assert_eq!(loan.disbursed, loan_before.disbursed);
+    assert_eq!(upgraded.get_loan_request(&other_farmer).amount, 5000);
+
+    upgraded.pay_installment(&farmer, &1000);
+    assert_eq!(upgraded.get_loan(&farmer).paid_principal, loan_before.paid_principal + 1000);
+}
+
+#[test]
+fn test_upgrade_requires_admin() {
+    let (env, client) = setup();
+
+    let attacker = Address::generate(&env);
+    let new_wasm_hash = env.deployer().upload_contract_wasm(payment_wasm::WASM);
+    assert_eq!(client.try_upgrade(&attacker, &new_wasm_hash), Err(Ok(Error::Unauthorized)));
+}
+
+#[test]
+fn test_migrate_rewrites_version_1_loans() {
+    let (env, client) = setup();
+    let admin = client.get_admin();
+    let farmer = Address::generate(&env);
+    let untouched_farmer = Address::generate(&env);
+
+    // Loans as a version 1 deployment stored them.
+    for farmer in [&farmer, &untouched_farmer] {
+        client.request_credit(farmer, &10000, &5, &2);
+        client.accept_credit(&client.get_admin(), farmer, &12);
+    }
+    env.as_contract(&client.address, || {
+        for farmer in [&farmer, &untouched_farmer] {
+            let key = (LoanType::Loan, farmer.clone());
+            let loan: Loan = env.storage().persistent().get(&key).unwrap();
+            let old = LoanV1 {
+                farmer: loan.farmer,
+                principal: loan.principal,
+                interest_rate: loan.interest_rate,
+                total_interest_paid: loan.total_interest_paid,
+                paid_principal: 500,
+                term_in_months: loan.term_in_months,
+                start_date: loan.start_date,
+            };
+            env.storage().persistent().set(&key, &old);
+            // Version 1 paid loans out of the contract's own USDC.
+            env.storage().persistent().remove(&(LoanType::Drawn, farmer.clone()));
+        }
+        env.storage().persistent().remove(&DataKey::Version);
+    });
+    assert_eq!(client.get_version(), 1);
+
+    let stranger = Address::generate(&env);
+    assert_eq!(client.migrate(&admin, &vec![&env, farmer.clone(), stranger]), 1);
+    assert_eq!(client.get_version(), SCHEMA_VERSION);
+
+    let loan: Loan = env.as_contract(&client.address, || {
+        env.storage().persistent().get(&(LoanType::Loan, farmer.clone())).unwrap()
+    });
+    assert_eq!(loan.disbursed, 9000);
+    assert_eq!(loan.paid_principal, 500);
+
+    // Loans left out of the migration are converted when first read.
+    let loan = client.get_loan(&untouched_farmer);
+    assert_eq!(loan.disbursed, 9000);
+    assert_eq!(loan.paid_principal, 500);
+}
+
+#[test]
+fn test_migrate_requires_admin() {
+    let (env, client) = setup();
+
+    let attacker = Address::generate(&env);
+    assert_eq!(client.try_migrate(&attacker, &vec![&env]), Err(Ok(Error::Unauthorized)));
+}
+
+#[test]
+fn test_pause_blocks_loan_entrypoints() {
+    let (env, client) = setup();
+    let admin = client.get_admin();
+    let farmer = Address::generate(&env);
+    let other_farmer = Address::generate(&env);
+
+    client.request_credit(&farmer, &10000, &5, &2);
+    client.accept_credit(&client.get_admin(), &farmer, &12);
+    client.request_credit(&other_farmer, &5000, &5, &2);
+
+    client.set_paused(&admin, &true);
+    assert!(client.is_paused());
+
+    assert_eq!(client.try_request_credit(&farmer, &100, &5, &2), Err(Ok(Error::Paused)));
+    assert_eq!(client.try_accept_credit(&client.get_admin(), &other_farmer, &12), Err(Ok(Error::Paused)));
+    assert_eq!(client.try_pay_installment(&farmer, &1000), Err(Ok(Error::Paused)));
+    assert_eq!(client.try_pay_off_loan(&farmer), Err(Ok(Error::Paused)));
+
+    client.set_paused(&admin, &false);
+    client.accept_credit(&client.get_admin(), &other_farmer, &12);
+    client.pay_off_loan(&farmer);
+}
+
+#[test]
+fn test_function_pause_only_blocks_that_function() {
+    let (env, client) = setup();
+    let admin = client.get_admin();
+    let farmer = Address::generate(&env);
+
+    client.set_function_paused(&admin, &PausableFunction::AcceptCredit, &true);
+    assert!(!client.is_paused());
+    assert!(client.is_function_paused(&PausableFunction::AcceptCredit));
+
+    client.request_credit(&farmer, &10000, &5, &2);
+    assert_eq!(client.try_accept_credit(&client.get_admin(), &farmer, &12), Err(Ok(Error::Paused)));
+
+    client.set_function_paused(&admin, &PausableFunction::AcceptCredit, &false);
+    client.accept_credit(&client.get_admin(), &farmer, &12);
+}
+
+#[test]
+fn test_pause_requires_pauser() {
+    let (env, client) = setup();
+    let admin = client.get_admin();
+    let pauser = Address::generate(&env);
+
+    assert!(!client.is_pauser(&pauser));
+    assert_eq!(client.try_set_paused(&pauser, &true), Err(Ok(Error::Unauthorized)));
+    assert_eq!(client.try_set_pauser(&pauser, &pauser, &true), Err(Ok(Error::Unauthorized)));
+
+    client.set_pauser(&admin, &pauser, &true);
+    assert!(client.is_pauser(&pauser));
+    client.set_function_paused(&pauser, &PausableFunction::PayOffLoan, &true);
+    assert!(client.is_function_paused(&PausableFunction::PayOffLoan));
+
+    client.set_pauser(&admin, &pauser, &false);
+    assert_eq!(
+        client.try_set_function_paused(&pauser, &PausableFunction::PayOffLoan, &false),
+        Err(Ok(Error::Unauthorized))
+    );
+}
+
+#[test]
+fn test_pause_toggles_emit_events() {
+    let (env, client) = setup();
+    let admin = client.get_admin();
+
+    client.set_paused(&admin, &true);
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            payment_event(
+                &client,
+                "pause_updated",
+                &admin,
+                PauseUpdated {
+                    scope: PauseScope::All,
+                    paused: true,
+                },
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_loan_lifecycle_emits_events() {
+    let (env, client) = setup();
+    let farmer = Address::generate(&env);
+
+    client.request_credit(&farmer, &10000, &5, &2);
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            payment_event(
+                &client,
+                "credit_requested",
+                &farmer,
+                CreditRequested {
+                    amount: 10000,
+                    interest_rate: 5,
+                    loan_fee: 2,
+                },
+            ),
+        ]
+    );
+
+    client.accept_credit(&client.get_admin(), &farmer, &12);
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            payment_event(
+                &client,
+                "credit_accepted",
+                &farmer,
+                CreditAccepted {
+                    principal: 9180,
+                    disbursed: 9000,
+                    interest_rate: 5,
+                    term_in_months: 12,
+                },
+            ),
+        ]
+    );
+
+    client.pay_installment(&farmer, &1000);
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            payment_event(
+                &client,
+                "installment_paid",
+                &farmer,
+                InstallmentPaid {
+                    amount: 1000,
+                    interest: 8180 * 5 / 100,
+                    subsidy: 0,
+                    remaining_principal: 8180,
+                },
+            ),
+        ]
+    );
+
+    let amount = client.pay_off_loan(&farmer);
+    assert_eq!(
+        last_event(&env),
+        vec![&env, payment_event(&client, "loan_paid_off", &farmer, LoanPaidOff { amount })]
+    );
+}
+
+/// Gives `farmer` an accepted loan of 9180 principal, 9000 disbursed, with
+/// 1000 of it repaid.
+fn setup_defaulted_loan(client: &PaymentContractClient, farmer: &Address) {
+    client.request_credit(farmer, &10000, &5, &2);
+    client.accept_credit(&client.get_admin(), farmer, &12);
+    client.pay_installment(farmer, &1000);
+}
+
+#[test]
+fn test_write_off_needs_admin() {
+    let (env, client) = setup();
+    let farmer = Address::generate(&env);
+    let admin = client.get_admin();
+
+    client.request_credit(&farmer, &10000, &5, &2);
+    client.accept_credit(&client.get_admin(), &farmer, &12);
+
+    let outsider = Address::generate(&env);
+    assert_eq!(client.try_write_off_loan(&outsider, &farmer), Err(Ok(Error::Unauthorized)));
+    assert_eq!(
+        client.try_write_off_loan(&admin, &Address::generate(&env)),
+        Err(Ok(Error::LoanNotFound))
+    );
+}
+
+#[test]
+fn test_write_off_reports_lent_capital_to_fund() {
+    let (env, client) = setup();
+    let farmer = Address::generate(&env);
+    setup_defaulted_loan(&client, &farmer);
+
+    let loss = client.write_off_loan(&client.get_admin(), &farmer);
+    assert_eq!(
+        last_event(&env),
+        vec![&env, payment_event(&client, "loan_written_off", &farmer, LoanWrittenOff { loss: 8000, guaranteed: 0 })]
+    );
+    assert_eq!(loss, 8000);
+    assert_eq!(fund(&client).last(), (Symbol::new(&env, "write_off"), farmer.clone(), vec![&env, 8000]));
+
+    assert_eq!(client.get_written_off(&farmer), 8000);
+    assert_eq!(client.get_deployed_capital(), 0);
+    assert_eq!(client.try_get_loan(&farmer).err(), Some(Ok(Error::LoanNotFound)));
+}
+
+#[test]
+fn test_write_off_claims_the_guarantee_first() {
+    let (env, client) = setup();
+    let farmer = Address::generate(&env);
+    setup_defaulted_loan(&client, &farmer);
+    fund(&client).set_guarantee(&3000);
+
+    let loss = client.write_off_loan(&client.get_admin(), &farmer);
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            payment_event(&client, "loan_written_off", &farmer, LoanWrittenOff { loss: 8000, guaranteed: 3000 }),
+        ]
+    );
+    assert_eq!(loss, 8000);
+    assert_eq!(fund(&client).last(), (Symbol::new(&env, "write_off"), farmer.clone(), vec![&env, 5000]));
+
+    // The farmer still owes everything, whoever covered it.
+    assert_eq!(client.get_written_off(&farmer), 8000);
+
+    let other = Address::generate(&env);
+    setup_defaulted_loan(&client, &other);
+    fund(&client).set_guarantee(&10000);
+    client.write_off_loan(&client.get_admin(), &other);
+    assert_eq!(
+        fund(&client).last(),
+        (Symbol::new(&env, "claim_guarantee"), other, vec![&env, 8000, 8000])
+    );
+}
+
+#[test]
+fn test_recover_loan_pays_the_fund() {
+    let (env, client) = setup();
+    let farmer = Address::generate(&env);
+    let payer = Address::generate(&env);
+    setup_defaulted_loan(&client, &farmer);
+    client.write_off_loan(&client.get_admin(), &farmer);
+
+    assert_eq!(client.try_recover_loan(&payer, &farmer, &8001), Err(Ok(Error::InvalidAmount)));
+
+    let outstanding = client.recover_loan(&payer, &farmer, &3000);
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            payment_event(
+                &client,
+                "loan_recovered",
+                &farmer,
+                LoanRecovered {
+                    payer: payer.clone(),
+                    amount: 3000,
+                    outstanding: 5000,
+                },
+            ),
+        ]
+    );
+    assert_eq!(outstanding, 5000);
+    assert_eq!(fund(&client).last(), (Symbol::new(&env, "recover"), farmer.clone(), vec![&env, 3000]));
+
+    assert_eq!(client.recover_loan(&payer, &farmer, &5000), 0);
+    assert_eq!(client.get_written_off(&farmer), 0);
+    assert_eq!(client.try_recover_loan(&payer, &farmer, &1), Err(Ok(Error::LoanNotFound)));
+}
+
+#[test]
+fn test_loans_draw_capital_from_fund() {
+    let (env, client) = setup();
+    let farmer = Address::generate(&env);
+
+    client.request_credit(&farmer, &10000, &5, &2);
+    client.accept_credit(&client.get_admin(), &farmer, &12);
+    assert_eq!(fund(&client).last(), (Symbol::new(&env, "draw_capital"), farmer.clone(), vec![&env, 9000]));
+    assert_eq!(client.get_deployed_capital(), 9000);
+
+    env.as_contract(&client.address, || env.storage().persistent().remove(&DataKey::Fund));
+    let other = Address::generate(&env);
+    client.request_credit(&other, &10000, &5, &2);
+    assert_eq!(client.try_accept_credit(&client.get_admin(), &other, &12), Err(Ok(Error::FundNotSet)));
+}
+
+#[test]
+fn test_repayments_return_capital_then_income_to_fund() {
+    let (env, client) = setup();
+    let farmer = Address::generate(&env);
+    client.request_credit(&farmer, &10000, &5, &2);
+    client.accept_credit(&client.get_admin(), &farmer, &12);
+
+    // Principal repays the 9000 lent out first; interest is income.
+    client.pay_installment(&farmer, &5000);
+    assert_eq!(
+        fund(&client).last(),
+        (Symbol::new(&env, "repay_capital"), farmer.clone(), vec![&env, 5000, 4180 * 5 / 100])
+    );
+    assert_eq!(client.get_deployed_capital(), 4000);
+
+    // The last 180 of principal is the loan fee, so it is income too.
+    client.pay_installment(&farmer, &4100);
+    assert_eq!(
+        fund(&client).last(),
+        (Symbol::new(&env, "repay_capital"), farmer.clone(), vec![&env, 4000, 100 + 80 * 5 / 100])
+    );
+    assert_eq!(client.get_deployed_capital(), 0);
+
+    assert_eq!(client.pay_off_loan(&farmer), 80);
+    assert_eq!(
+        fund(&client).last(),
+        (Symbol::new(&env, "repay_capital"), farmer.clone(), vec![&env, 0, 80])
+    );
+}
+
+#[test]
+fn test_subsidy_lowers_what_the_farmer_pays() {
+    let (env, client) = setup();
+    let farmer = Address::generate(&env);
+    client.request_credit(&farmer, &10000, &5, &2);
+    client.accept_credit(&client.get_admin(), &farmer, &12);
+    fund(&client).set_subsidy_bps(&4000);
+
+    client.pay_installment(&farmer, &1000);
+    let interest = 8180 * 5 / 100;
+    let subsidy = interest * 4000 / 10_000;
+    assert_eq!(
+        last_event(&env),
+        vec![
+            &env,
+            payment_event(
+                &client,
+                "installment_paid",
+                &farmer,
+                InstallmentPaid {
+                    amount: 1000,
+                    interest,
+                    subsidy,
+                    remaining_principal: 8180,
+                },
+            ),
+        ]
+    );
+    // The fund is repaid the principal and only the unsubsidized interest.
+    assert_eq!(
+        fund(&client).last(),
+        (Symbol::new(&env, "repay_capital"), farmer.clone(), vec![&env, 1000, interest - subsidy])
+    );
+    assert_eq!(client.get_loan(&farmer).total_interest_paid, interest);
+}
+
+/// The payment contract wired to a real fund, both on a real USDC token.
+/// An investor has put `senior` USDC into the fund's senior tranche.
+fn setup_with_fund(senior: i128) -> (Env, PaymentContractClient<'static>, FundContractClient<'static>) {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let usdc_id = env.register_stellar_asset_contract_v2(Address::generate(&env)).address();
+    let payment = PaymentContractClient::new(&env, &env.register(PaymentContract, ()));
+    let fund = FundContractClient::new(&env, &env.register(FundContract, ()));
+    let admin = Address::generate(&env);
+
+    payment.init(&admin, &Address::generate(&env), &Address::generate(&env), &usdc_id);
+    // All senior, so investing needs no swap and no Blend.
+    let allocation = Allocation {
+        senior_bps: 10_000,
+        subordinated_bps: 0,
+        reserve_bps: 0,
+        donation_bps: 0,
+    };
+    let (router, comet, xlm) = (Address::generate(&env), Address::generate(&env), Address::generate(&env));
+    fund.init(&admin, &router, &comet, &usdc_id, &xlm, &allocation);
+    fund.set_payment_contract(&admin, &payment.address);
+    payment.set_fund(&admin, &fund.address);
+
+    let investor = Address::generate(&env);
+    StellarAssetClient::new(&env, &usdc_id).mint(&investor, &senior);
+    let swap = SwapParams {
+        amount_out_min: 0,
+        path: vec![&env],
+        deadline: 0,
+    };
+    fund.invest(&investor, &senior, &swap);
+
+    (env, payment, fund)
+}
+
+fn usdc(client: &PaymentContractClient) -> Address {
+    let addresses: ContractAddresses = client.env.as_contract(&client.address, || {
+        client.env.storage().persistent().get(&DataKey::Addresses).unwrap()
+    });
+    addresses.usdc_token
+}
+
+#[test]
+fn test_loans_run_through_the_fund() {
+    let (env, client, fund) = setup_with_fund(20_000);
+    let usdc = TokenClient::new(&env, &usdc(&client));
+    let farmer = Address::generate(&env);
+
+    client.request_credit(&farmer, &10000, &5, &2);
+    client.accept_credit(&client.get_admin(), &farmer, &12);
+    assert_eq!(usdc.balance(&farmer), 9000);
+    let state = fund.get_state();
+    assert_eq!((state.total_senior, state.loans_receivable), (11_000, 9000));
+
+    // 1000 of capital and 5% on the 8180 still owed.
+    StellarAssetClient::new(&env, &usdc.address).mint(&farmer, &1000);
+    client.pay_installment(&farmer, &1000);
+    let state = fund.get_state();
+    assert_eq!((state.total_senior, state.loans_receivable, state.total_yield), (12_409, 8000, 409));
+
+    // The rest of the principal, 180 of it the loan fee; its interest was
+    // paid with the installment.
+    assert_eq!(client.pay_off_loan(&farmer), 8180);
+    let state = fund.get_state();
+    assert_eq!((state.total_senior, state.loans_receivable, state.total_yield), (20_589, 0, 589));
+    assert_eq!(client.get_deployed_capital(), 0);
+    assert_eq!(usdc.balance(&client.address), 0);
+    assert_eq!(usdc.balance(&fund.address), 20_589);
+}
+
+#[test]
+fn test_loans_from_before_the_fund_settle_locally() {
+    let (env, client, fund) = setup_with_fund(20_000);
+    let usdc = TokenClient::new(&env, &usdc(&client));
+    let minter = StellarAssetClient::new(&env, &usdc.address);
+    let farmer = Address::generate(&env);
+    let legacy = Address::generate(&env);
+
+    client.request_credit(&farmer, &10000, &5, &2);
+    client.accept_credit(&client.get_admin(), &farmer, &12);
+
+    // A loan a version 1 deployment paid out of its own USDC.
+    env.as_contract(&client.address, || {
+        let request = LoanRequest {
+            farmer: legacy.clone(),
+            amount: 10000,
+            interest_rate: 5,
+            loan_fee: 2,
+            approved: true,
+        };
+        env.storage().persistent().set(&(LoanType::Request, legacy.clone()), &request);
+        let loan = LoanV1 {
+            farmer: legacy.clone(),
+            principal: 9180,
+            interest_rate: 5,
+            total_interest_paid: 0,
+            paid_principal: 0,
+            term_in_months: 12,
+            start_date: 0,
+        };
+        env.storage().persistent().set(&(LoanType::Loan, legacy.clone()), &loan);
+    });
+    minter.mint(&legacy, &1409);
+
+    let before = fund.get_state();
+    client.pay_installment(&legacy, &1000);
+    assert_eq!(usdc.balance(&client.address), 1409);
+
+    assert_eq!(client.write_off_loan(&client.get_admin(), &legacy), 8000);
+    assert_eq!(fund.get_losses().senior, 0);
+
+    let payer = Address::generate(&env);
+    minter.mint(&payer, &500);
+    assert_eq!(client.recover_loan(&payer, &legacy, &500), 7500);
+    assert_eq!(usdc.balance(&client.address), 1909);
+
+    // The fund never saw the old loan, and its own loan is untouched.
+    let after = fund.get_state();
+    assert_eq!(after.loans_receivable, 9000);
+    assert_eq!((after.total_senior, after.total_yield), (before.total_senior, before.total_yield));
+    assert_eq!(client.get_deployed_capital(), 9000);
+}
@@ -0,0 +1,39 @@
+//! TTL policy for the payment contract's ledger entries.
+//!
+//! The instance is bumped on every call. Config and loan entries are bumped
+//! whenever they are read or written, and admins can bump idle ones with
+//! `bump_all` and `extend_loan_ttl`.
+
+use soroban_sdk::{Env, IntoVal, Val};
+
+pub const DAY_IN_LEDGERS: u32 = 17_280;
+
+/// Installments are monthly, so everything a payment touches, the instance
+/// included, has to outlive the gap until the next one with room to spare.
+pub const PERSISTENT_BUMP_AMOUNT: u32 = 60 * DAY_IN_LEDGERS;
+pub const PERSISTENT_LIFETIME_THRESHOLD: u32 = PERSISTENT_BUMP_AMOUNT - DAY_IN_LEDGERS;
+
+pub const INSTANCE_BUMP_AMOUNT: u32 = PERSISTENT_BUMP_AMOUNT;
+pub const INSTANCE_LIFETIME_THRESHOLD: u32 = PERSISTENT_LIFETIME_THRESHOLD;
+
+pub fn extend_instance(env: &Env) {
+    env.storage()
+        .instance()
+        .extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+}
+
+/// Bumps a persistent entry that is known to exist.
+pub fn extend_persistent<K: IntoVal<Env, Val>>(env: &Env, key: &K) {
+    env.storage()
+        .persistent()
+        .extend_ttl(key, PERSISTENT_LIFETIME_THRESHOLD, PERSISTENT_BUMP_AMOUNT);
+}
+
+/// Bumps a persistent entry if it exists. Returns whether it did.
+pub fn extend_persistent_if_present<K: IntoVal<Env, Val>>(env: &Env, key: &K) -> bool {
+    let present = env.storage().persistent().has(key);
+    if present {
+        extend_persistent(env, key);
+    }
+    present
+}